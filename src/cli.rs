@@ -0,0 +1,175 @@
+use kmcrayfish::sampling;
+use kmcrayfish::sampling::Sampling;
+use kmcrayfish::sampling::SyncmerKind;
+use kmcrayfish::spaced::SpacedSeed;
+use kmcrayfish::split::AmbiguityPolicy;
+use kmcrayfish::split::MaskPolicy;
+use kmcrayfish::superkmer;
+use kmcrayfish::Config;
+
+pub const DEFAULT_KMER_LEN: usize = 31;
+pub const DEFAULT_MAX_VARIANTS: usize = 16;
+
+pub struct Args {
+    pub config: Config,
+    pub super_kmers: bool,
+    pub output: Option<String>,
+    pub filename: String,
+}
+
+pub fn usage() {
+    print!(
+        "Usage:
+    kmcrayfish [-k <kmer_len>] [--soft-mask <skip|ignore-case>]
+               [--ambiguous <split|expand[:<max>]|substitute:<base>>]
+               [--super-kmers] [--spaced-seed <pattern>]
+               [--sample <closed-syncmer|open-syncmer[:<offset>]|randstrobe:<min>:<max>>]
+               [-o <path>] <fasta_file>
+
+    -k <kmer_len>   k-mer length, 1 to 127, but 1 to 64, 71 to 121 in steps
+                    of 10, or 127 with --spaced-seed or --sample (default {})
+    --soft-mask     skip lowercase (soft-masked) bases, or count them as
+                    uppercase with ignore-case (default skip)
+    --ambiguous     for IUPAC codes such as N: drop the k-mers covering them,
+                    count all variants of k-mers with at most <max> of them
+                    (default {}), or read them as <base> (default split)
+    --super-kmers   send super-k-mers, runs of k-mers sharing a minimizer of
+                    length {}, instead of single k-mers to cut traffic
+    --spaced-seed   count spaced k-mers of the care (1) positions of a pattern
+                    such as 1101101, k is the number of care positions
+    --sample        count only syncmers, k-mers whose smallest (k-4)-mer is at
+                    either end or at <offset> (default the middle), or
+                    randstrobes of two k/2-mers, the second starting <min> to
+                    <max> bases after the first or, on the reverse strand,
+                    before it, within windows of 2*<max>+k/2 bases
+    -o, --output    write the counts of each place as k-mer<TAB>count lines
+                    to <path>.<place>, or <path without .zst>.<place>.zst
+                    compressed with zstd if <path> ends in .zst
+    ",
+        DEFAULT_KMER_LEN,
+        DEFAULT_MAX_VARIANTS,
+        superkmer::MINIMIZER_LEN
+    );
+}
+
+fn parse_ambiguity(policy: &str) -> Option<AmbiguityPolicy> {
+    let (name, value) = match policy.find(':') {
+        Some(i) => (&policy[..i], Some(&policy[i + 1..])),
+        None => (policy, None),
+    };
+    Some(match (name, value) {
+        ("split", None) => AmbiguityPolicy::Split,
+        ("expand", None) => AmbiguityPolicy::Expand(DEFAULT_MAX_VARIANTS),
+        ("expand", Some(max)) => AmbiguityPolicy::Expand(max.parse().ok()?),
+        ("substitute", Some(base)) => match base.to_ascii_uppercase().as_bytes() {
+            [base @ (b'A' | b'C' | b'G' | b'T' | b'U')] => AmbiguityPolicy::Substitute(*base),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+fn parse_sampling(sampling: &str, kmer_len: usize) -> Option<Sampling> {
+    let mut parts = sampling.split(':');
+    let smer_len = sampling::syncmer_smer_len(kmer_len);
+    let sampling = match (parts.next()?, parts.next(), parts.next()) {
+        ("closed-syncmer", None, None) => Sampling::Syncmer(SyncmerKind::Closed),
+        ("open-syncmer", None, None) => {
+            Sampling::Syncmer(SyncmerKind::Open(kmer_len.saturating_sub(smer_len) / 2))
+        }
+        ("open-syncmer", Some(offset), None) => {
+            Sampling::Syncmer(SyncmerKind::Open(offset.parse().ok()?))
+        }
+        ("randstrobe", Some(min), Some(max)) => {
+            Sampling::Randstrobe(min.parse().ok()?, max.parse().ok()?)
+        }
+        _ => return None,
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some(sampling),
+    }
+}
+
+pub fn parse_args() -> Result<Args, String> {
+    let mut kmer_len = DEFAULT_KMER_LEN;
+    let mut mask = MaskPolicy::SkipMasked;
+    let mut ambiguity = AmbiguityPolicy::Split;
+    let mut super_kmers = false;
+    let mut spaced_seed = None;
+    let mut kmer_len_given = false;
+    let mut sampling = None;
+    let mut output = None;
+    let mut filename = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-k" => {
+                let k = args.next().ok_or("-k requires a value")?;
+                kmer_len = k
+                    .parse()
+                    .map_err(|_| format!("invalid k-mer length: {}", k))?;
+                kmer_len_given = true;
+            }
+            "--soft-mask" => {
+                mask = match args.next().as_deref() {
+                    Some("skip") => MaskPolicy::SkipMasked,
+                    Some("ignore-case") => MaskPolicy::IgnoreCase,
+                    _ => return Err("--soft-mask expects skip or ignore-case".to_string()),
+                };
+            }
+            "--ambiguous" => {
+                let policy = args.next().ok_or("--ambiguous requires a value")?;
+                ambiguity = parse_ambiguity(&policy)
+                    .ok_or_else(|| format!("invalid ambiguity policy: {}", policy))?;
+            }
+            "--super-kmers" => super_kmers = true,
+            "--spaced-seed" => {
+                let pattern = args.next().ok_or("--spaced-seed requires a pattern")?;
+                let seed = SpacedSeed::parse(&pattern)
+                    .ok_or_else(|| format!("invalid spaced seed: {}", pattern))?;
+                spaced_seed = Some(seed);
+            }
+            "--sample" => sampling = Some(args.next().ok_or("--sample requires a value")?),
+            "-o" | "--output" => output = Some(args.next().ok_or("--output requires a path")?),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    // k defaults to the seed weight, Config::check catches a -k that differs
+    if let (Some(seed), false) = (&spaced_seed, kmer_len_given) {
+        kmer_len = seed.weight();
+    }
+    // super-k-mers are a front-end matter, Config::check does not know them
+    if super_kmers && (spaced_seed.is_some() || sampling.is_some()) {
+        return Err("--super-kmers cannot be combined with --spaced-seed or --sample".to_string());
+    }
+    if super_kmers && kmer_len < superkmer::MINIMIZER_LEN {
+        return Err(format!(
+            "--super-kmers needs k-mers of at least {} bases",
+            superkmer::MINIMIZER_LEN
+        ));
+    }
+    let sampling = match sampling {
+        Some(sampling) => Some(
+            parse_sampling(&sampling, kmer_len)
+                .ok_or_else(|| format!("invalid sampling: {}", sampling))?,
+        ),
+        None => None,
+    };
+    let config = Config {
+        kmer_len,
+        mask,
+        ambiguity,
+        spaced_seed,
+        sampling,
+    };
+    config.check()?;
+    let filename = filename.ok_or("missing input file")?;
+    Ok(Args {
+        config,
+        super_kmers,
+        output,
+        filename,
+    })
+}
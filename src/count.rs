@@ -0,0 +1,716 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::input;
+use crate::input::Compression;
+use crate::kmer;
+use crate::kmer::radix::RadixSort;
+use crate::kmer::AbstractKMer;
+use crate::kmer::CanonicalKmers;
+use crate::kmer::InvertibleHash;
+use crate::kmer::KMerOf;
+use crate::kmer::KmerHasher;
+use crate::kmer::RNA;
+use crate::reader;
+use crate::reader::SeqReader;
+use crate::sampling;
+use crate::sampling::Randstrobes;
+use crate::sampling::Sampling;
+use crate::sampling::SyncmerKind;
+use crate::sampling::Syncmers;
+use crate::spaced::SpacedKmers;
+use crate::spaced::SpacedSeed;
+use crate::split::AmbiguityPolicy;
+use crate::split::KMerSplitter;
+use crate::split::MaskPolicy;
+use crate::split::SplitStats;
+use crate::superkmer;
+use crate::superkmer::SuperKmers;
+use crate::superkmer::MINIMIZER_LEN;
+use crate::superkmer::MINIMIZER_ORDER;
+
+// reads both T and U, so transcriptome input counts together with DNA
+pub type Bases = RNA<b'T'>;
+pub type KMer<const N: usize> = KMerOf<Bases, N>;
+pub type Minimizer = KMer<MINIMIZER_LEN>;
+
+// both binaries and every place partition alike
+const PARTITION_HASHER: InvertibleHash = InvertibleHash::new(0);
+
+/// The partition, out of `partitions`, a k-mer is counted in.
+pub fn partition<K: AbstractKMer>(kmer: &K, partitions: usize) -> usize {
+    (PARTITION_HASHER.hash(kmer) % partitions as u64) as usize
+}
+
+/// `partition` of a k-mer given by its raw words.
+pub fn partition_words(words: &[u64], partitions: usize) -> usize {
+    (PARTITION_HASHER.hash_words(words) % partitions as u64) as usize
+}
+
+/// What gets counted: the k-mer length and how reads are split into k-mers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub kmer_len: usize,
+    pub mask: MaskPolicy,
+    pub ambiguity: AmbiguityPolicy,
+    /// Count spaced k-mers instead, `kmer_len` is the weight of the seed.
+    pub spaced_seed: Option<SpacedSeed>,
+    /// Count only the sampled k-mers.
+    pub sampling: Option<Sampling>,
+}
+
+impl Config {
+    /// Every k-mer of length `kmer_len`, skipping soft-masked bases and
+    /// splitting reads at ambiguity codes.
+    pub fn new(kmer_len: usize) -> Self {
+        Config {
+            kmer_len,
+            mask: MaskPolicy::SkipMasked,
+            ambiguity: AmbiguityPolicy::Split,
+            spaced_seed: None,
+            sampling: None,
+        }
+    }
+
+    /// Whether the options go together, and why not.
+    pub fn check(&self) -> Result<(), String> {
+        let k = self.kmer_len;
+        if !kmer::kmer_len_supported(k) {
+            return Err(format!("unsupported k-mer length: {}", k));
+        }
+        if (self.spaced_seed.is_some() || self.sampling.is_some())
+            && !kmer::sampled_kmer_len_supported(k)
+        {
+            let long: Vec<String> = kmer::SAMPLED_LONG_KMER_LENS
+                .iter()
+                .map(|k| k.to_string())
+                .collect();
+            return Err(format!(
+                "{}-mers cannot be spaced or sampled, only k of 1 to 64 or {}",
+                k,
+                long.join(", ")
+            ));
+        }
+        self.ambiguity.check::<Bases>()?;
+        let expand = matches!(self.ambiguity, AmbiguityPolicy::Expand(_));
+        if let Some(seed) = &self.spaced_seed {
+            if seed.weight() != k {
+                return Err(format!(
+                    "{}-mers do not match the {} care positions of the spaced seed",
+                    k,
+                    seed.weight()
+                ));
+            }
+            if expand || self.sampling.is_some() {
+                return Err("spaced seeds cannot be expanded or sampled".to_string());
+            }
+        }
+        match self.sampling {
+            Some(Sampling::Syncmer(SyncmerKind::Open(offset)))
+                if offset > k - sampling::syncmer_smer_len(k) =>
+            {
+                Err(format!(
+                    "open syncmer offset {} is past the {}-mer",
+                    offset, k
+                ))
+            }
+            Some(Sampling::Randstrobe(min, max)) if k % 2 == 1 || min == 0 || min > max => Err(
+                format!("invalid randstrobes {}:{} for {}-mers", min, max, k),
+            ),
+            Some(Sampling::Randstrobe(..)) if expand => {
+                Err("randstrobes cannot be expanded".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Bases a counted k-mer covers, for randstrobes the window the second
+    /// strobe is picked in, either side of the first.
+    pub fn span(&self) -> usize {
+        match (&self.spaced_seed, self.sampling) {
+            (Some(seed), _) => seed.span(),
+            (None, Some(Sampling::Randstrobe(_, max_offset))) => {
+                2 * max_offset + self.kmer_len.div_ceil(2)
+            }
+            (None, _) => self.kmer_len,
+        }
+    }
+
+    /// Reads the sequences of FASTA or FASTQ input to count, long FASTA
+    /// records in pieces where that loses no k-mer.
+    pub fn seq_reader<R: BufRead>(&self, reader: R) -> io::Result<SeqReader<R>> {
+        SeqReader::chunked(reader, self.span() - 1)
+    }
+}
+
+/// Splits reads into the k-mers a `Config` counts, handed out as raw words so
+/// callers need not dispatch on the k-mer length.
+pub struct ReadSplitter {
+    config: Config,
+    splitter: KMerSplitter,
+    words: Vec<u64>,
+}
+
+impl ReadSplitter {
+    /// Panics unless `config` passes `Config::check`.
+    pub fn new(config: Config) -> Self {
+        if let Err(e) = config.check() {
+            panic!("{}", e);
+        }
+        ReadSplitter {
+            splitter: KMerSplitter::new(config.mask, config.ambiguity),
+            config,
+            words: vec![],
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn stats(&self) -> SplitStats {
+        self.splitter.stats()
+    }
+
+    /// Calls `emit` with the raw words of every k-mer counted in `read`.
+    pub fn kmers<F: FnMut(&[u64])>(&mut self, read: &[u8], mut emit: F) {
+        let ReadSplitter {
+            config,
+            splitter,
+            words,
+        } = self;
+        if config.spaced_seed.is_none() && config.sampling.is_none() {
+            crate::dispatch_kmer_len!(config.kmer_len, N => splitter.split(read, |k: KMer<N>| {
+                words.clear();
+                k.to_words(words);
+                emit(words)
+            }), _ => unreachable!());
+            return;
+        }
+        crate::dispatch_kmer_len!(@sampled config.kmer_len, N => {
+            let mut emit_kmer = |k: KMer<N>| {
+                words.clear();
+                k.to_words(words);
+                emit(words)
+            };
+            if let Some(seed) = &config.spaced_seed {
+                splitter.split_runs::<KMer<N>, _>(read, |run| {
+                    for (_, k, _) in SpacedKmers::<KMer<N>>::new(run, seed) {
+                        emit_kmer(k)
+                    }
+                })
+            } else if let Some(sampling) = config.sampling {
+                splitter.split_runs::<KMer<N>, _>(read, |run| match sampling {
+                    Sampling::Syncmer(kind) => {
+                        for (_, k) in Syncmers::<KMer<N>, KMer<{ sampling::syncmer_smer_len(N) }>>::new(run, kind) {
+                            emit_kmer(k)
+                        }
+                    }
+                    // odd k fails the check, rounding up only keeps k = 1 building
+                    Sampling::Randstrobe(min_offset, max_offset) => {
+                        for (_, k) in Randstrobes::<KMer<N>, KMer<{ N.div_ceil(2) }>>::new(run, min_offset, max_offset) {
+                            emit_kmer(k)
+                        }
+                    }
+                })
+            }
+        }, _ => unreachable!());
+    }
+
+    /// Calls `emit` with the super-k-mers of `read` and their minimizers
+    /// instead, see `SuperKmers`. Spaced seeds and sampling do not apply.
+    pub fn super_kmers<F: FnMut(&Minimizer, &[u8])>(&mut self, read: &[u8], mut emit: F) {
+        let k = self.config.kmer_len;
+        assert!(k >= MINIMIZER_LEN, "k-mers are shorter than the minimizers");
+        let splitter = &mut self.splitter;
+        crate::dispatch_kmer_len!(k, N => splitter.split_runs::<KMer<N>, _>(read, |run| {
+            for (minimizer, bases) in SuperKmers::<Minimizer>::new(run, N, MINIMIZER_ORDER) {
+                emit(&minimizer, bases)
+            }
+        }), _ => unreachable!());
+    }
+}
+
+/// Appends the raw words of the canonical k-mers of length `kmer_len` in the
+/// super-k-mers `superkmer::pack` put in `packed`.
+pub fn unpack_super_kmers(kmer_len: usize, packed: &[u64], words: &mut Vec<u64>) {
+    crate::dispatch_kmer_len!(kmer_len, N => superkmer::unpack::<Bases, _>(packed, |bases| {
+        for (_, k, _) in CanonicalKmers::<KMer<N>>::new(bases) {
+            k.to_words(words)
+        }
+    }), _ => panic!("unsupported k-mer length: {}", kmer_len));
+}
+
+/// Distinct k-mers, as raw words in increasing order, with their counts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountTable {
+    kmer_len: usize,
+    words_per_kmer: usize,
+    words: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl CountTable {
+    /// Counts the k-mers of length `kmer_len` given by their raw words,
+    /// sorting `words` on the way.
+    pub fn from_words(kmer_len: usize, words: &mut [u64]) -> Self {
+        crate::dispatch_kmer_len!(kmer_len, N => Self::count::<KMer<N>>(words),
+            _ => panic!("unsupported k-mer length: {}", kmer_len))
+    }
+
+    fn count<K: AbstractKMer>(words: &mut [u64]) -> Self {
+        let w = K::WORDS;
+        if w == 1 {
+            // a single word orders like the k-mer, so radix sort it in place
+            words.voracious_sort();
+        } else {
+            let mut kmers: Vec<K> = words.chunks_exact(w).map(K::from_words).collect();
+            kmers.sort_unstable();
+            let mut sorted = Vec::with_capacity(words.len());
+            for kmer in kmers {
+                kmer.to_words(&mut sorted);
+            }
+            words.copy_from_slice(&sorted);
+        }
+        let mut table = CountTable {
+            kmer_len: K::kmer_len(),
+            words_per_kmer: w,
+            words: vec![],
+            counts: vec![],
+        };
+        for kmer in words.chunks_exact(w) {
+            match table.counts.last_mut() {
+                Some(count) if table.words.ends_with(kmer) => *count += 1,
+                _ => {
+                    table.words.extend_from_slice(kmer);
+                    table.counts.push(1);
+                }
+            }
+        }
+        table
+    }
+
+    pub fn kmer_len(&self) -> usize {
+        self.kmer_len
+    }
+
+    /// Number of distinct k-mers.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Number of k-mers counted, repeats included.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Iterates `(raw words, count)` in increasing k-mer order.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u64], u64)> + '_ {
+        self.words
+            .chunks_exact(self.words_per_kmer.max(1))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Iterates `(k-mer, count)` in increasing order. `K` must be the k-mer
+    /// type of the counted length, such as `KMer<N>`.
+    pub fn kmers<K: AbstractKMer>(&self) -> impl Iterator<Item = (K, u64)> + '_ {
+        assert!(
+            self.is_empty() || (K::kmer_len() == self.kmer_len && K::WORDS == self.words_per_kmer)
+        );
+        self.iter()
+            .map(|(words, count)| (K::from_words(words), count))
+    }
+
+    /// Count of the k-mer given by its raw words, 0 if it was not seen.
+    pub fn get_words(&self, words: &[u64]) -> u64 {
+        let w = self.words_per_kmer;
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match self.words[mid * w..(mid + 1) * w].cmp(words) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return self.counts[mid],
+            }
+        }
+        0
+    }
+
+    /// Count of a canonical k-mer, 0 if it was not seen.
+    pub fn get<K: AbstractKMer>(&self, kmer: &K) -> u64 {
+        let mut words = Vec::with_capacity(K::WORDS);
+        kmer.to_words(&mut words);
+        self.get_words(&words)
+    }
+
+    /// How many distinct k-mers occur 1 to `len` times, the number of k-mers
+    /// seen `c` times at `c - 1`.
+    pub fn histogram(&self, len: usize) -> Vec<usize> {
+        let mut hist = vec![0usize; len];
+        for &count in &self.counts {
+            if let Some(slot) = hist.get_mut(count as usize - 1) {
+                *slot += 1;
+            }
+        }
+        hist
+    }
+
+    /// Writes a `k-mer<TAB>count` line per distinct k-mer, in order.
+    pub fn write_tsv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        crate::dispatch_kmer_len!(self.kmer_len, N => {
+            for (kmer, count) in self.kmers::<KMer<N>>() {
+                writeln!(out, "{}\t{}", kmer, count)?;
+            }
+        }, _ => debug_assert!(self.is_empty()));
+        Ok(())
+    }
+
+    /// Writes the table to the file at `path` as by `write_tsv`, compressed
+    /// with zstd if the name ends in `.zst`.
+    pub fn dump<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = BufWriter::new(File::create(path)?);
+        if path.extension() == Some("zst".as_ref()) {
+            let mut encoder = zstd::Encoder::new(file, 0)?;
+            self.write_tsv(&mut encoder)?;
+            file = encoder.finish()?;
+        } else {
+            self.write_tsv(&mut file)?;
+        }
+        file.flush()
+    }
+}
+
+/// Where the `part`th of several places dumps its share of the counts given
+/// the output `path`: `path.<part>`, or `<path>.<part>.zst` for a `path`
+/// ending in `.zst` so the dump stays compressed.
+pub fn part_path<P: AsRef<Path>>(path: P, part: usize) -> PathBuf {
+    let path = path.as_ref();
+    match path.extension() {
+        Some(ext) if ext == "zst" => path.with_extension(format!("{}.zst", part)),
+        _ => {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{}", part));
+            name.into()
+        }
+    }
+}
+
+/// The reads of the `part`th of `parts` places in the input `data`, a whole
+/// file as mapped into memory. Plain and BGZF input split into byte ranges
+/// of records, see `reader::split_input` and `input::bgzf_share`, and fail
+/// on FASTQ records wrapped over several lines, which they cannot split,
+/// while
+/// gzip, zstd and xz, which only decompress from the start, are read whole
+/// by each place keeping every `parts`th read, and every error so that no
+/// place counts past malformed input.
+pub fn share_reads<'a>(
+    config: &Config,
+    data: &'a [u8],
+    part: usize,
+    parts: usize,
+) -> io::Result<Box<dyn Iterator<Item = io::Result<Vec<u8>>> + 'a>> {
+    Ok(match Compression::detect(data) {
+        Compression::Plain => Box::new(
+            config
+                .seq_reader(reader::split_input(data, part, parts))?
+                .unwrapped(),
+        ),
+        Compression::Bgzf => Box::new(
+            config
+                .seq_reader(input::bgzf_share(data, part, parts)?)?
+                .unwrapped(),
+        ),
+        Compression::Gzip | Compression::Zstd | Compression::Xz => {
+            let reads = config.seq_reader(input::decompress(data)?)?;
+            Box::new(
+                reads
+                    .enumerate()
+                    .filter(move |(i, read)| read.is_err() || i % parts == part)
+                    .map(|(_, read)| read),
+            )
+        }
+    })
+}
+
+/// Counts the canonical k-mers of the FASTA or FASTQ files `inputs`, plain
+/// or compressed, in this process, the way the front-ends count them across
+/// places.
+pub fn count_kmers<I, P>(inputs: I, config: Config) -> io::Result<CountTable>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    config
+        .check()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let kmer_len = config.kmer_len;
+    let mut splitter = ReadSplitter::new(config);
+    let mut words = vec![];
+    for path in inputs {
+        let reads = splitter.config().seq_reader(input::open(path)?)?;
+        for read in reads {
+            splitter.kmers(&read?, |kmer| words.extend_from_slice(kmer));
+        }
+    }
+    Ok(CountTable::from_words(kmer_len, &mut words))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::KMerArr;
+    use crate::kmer::KMeru128;
+    use crate::kmer::KMeru64;
+    use crate::kmer::DNA;
+    use flate2::write::GzEncoder;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    const READ: &[u8] =
+        b"GATTACAGATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTTTACGGACTGAGATTACA";
+
+    fn words_of<K: AbstractKMer>(read: &[u8]) -> Vec<u64> {
+        let mut words = vec![];
+        for (_, kmer, _) in CanonicalKmers::<K>::new(read) {
+            kmer.to_words(&mut words);
+        }
+        words
+    }
+
+    fn brute_force<K: AbstractKMer>(read: &[u8]) -> BTreeMap<K, u64> {
+        let mut counts = BTreeMap::new();
+        for (_, kmer, _) in CanonicalKmers::<K>::new(read) {
+            *counts.entry(kmer).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    pub fn test_count_table() {
+        type K7 = KMeru64<DNA, 7>;
+        let expected = brute_force::<K7>(READ);
+        let mut words = words_of::<K7>(READ);
+        let table = CountTable::count::<K7>(&mut words);
+        assert!(words.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(table.kmers().collect::<BTreeMap<K7, _>>(), expected);
+        assert_eq!(table.total(), READ.len() as u64 - 6);
+        for (kmer, &count) in &expected {
+            assert_eq!(table.get(kmer), count);
+        }
+        assert_eq!(table.get(&K7::from_bytes(b"CCCCCCC").unwrap()), 0);
+        let hist = table.histogram(16);
+        assert_eq!(hist.iter().sum::<usize>(), expected.len());
+        assert_eq!(hist[0], expected.values().filter(|&&c| c == 1).count());
+        assert_eq!(table.histogram(1), hist[..1]);
+
+        // wider storage of the same k-mers counts the same
+        let mut words = words_of::<KMeru128<DNA, 7>>(READ);
+        assert_eq!(
+            CountTable::count::<KMeru128<DNA, 7>>(&mut words).histogram(16),
+            hist
+        );
+        let mut words = words_of::<KMerArr<DNA, 7, 2>>(READ);
+        assert_eq!(
+            CountTable::count::<KMerArr<DNA, 7, 2>>(&mut words).histogram(16),
+            hist
+        );
+
+        type K40 = KMerArr<DNA, 40, 2>;
+        let doubled = [READ, READ].concat();
+        let expected = brute_force::<K40>(&doubled);
+        let mut words = words_of::<K40>(&doubled);
+        let table = CountTable::count::<K40>(&mut words);
+        assert_eq!(table.kmers().collect::<BTreeMap<K40, _>>(), expected);
+        for (kmer, &count) in &expected {
+            assert_eq!(table.get(kmer), count);
+        }
+    }
+
+    #[test]
+    pub fn test_partition() {
+        let kmer = KMeru64::<DNA, 21>::from_bytes(READ).unwrap();
+        let words = words_of::<KMeru64<DNA, 21>>(&READ[..21]);
+        assert_eq!(KMeru64::<DNA, 21>::from_words(&words), kmer.get_canonical());
+        let wide = KMerArr::<DNA, 40, 2>::from_bytes(READ).unwrap();
+        let mut words = vec![];
+        wide.to_words(&mut words);
+        assert_eq!(words.len(), 2);
+        for partitions in 1..10 {
+            assert!(partition(&kmer, partitions) < partitions);
+            assert_eq!(
+                partition_words(&words, partitions),
+                partition(&wide, partitions)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_config_check() {
+        assert!(Config::new(31).check().is_ok());
+        assert!(Config::new(65).check().is_ok());
+        assert!(Config::new(128).check().is_err());
+        let mut config = Config::new(65);
+        config.sampling = Some(Sampling::Syncmer(SyncmerKind::Closed));
+        assert!(config.check().is_err());
+        assert_eq!(
+            config.check().unwrap_err(),
+            "65-mers cannot be spaced or sampled, only k of 1 to 64 or 71, 81, 91, 101, 111, 121, 127"
+        );
+        config.kmer_len = 71;
+        assert!(config.check().is_ok());
+        for k in 1..=128 {
+            assert_eq!(
+                kmer::sampled_kmer_len_supported(k),
+                k <= 64 || kmer::SAMPLED_LONG_KMER_LENS.contains(&k)
+            );
+        }
+        let mut config = Config::new(5);
+        config.spaced_seed = SpacedSeed::parse("11011");
+        assert!(config.check().is_err());
+        config.kmer_len = 4;
+        assert!(config.check().is_ok());
+        assert_eq!(config.span(), 5);
+        let mut config = Config::new(21);
+        config.sampling = Some(Sampling::Randstrobe(5, 10));
+        assert!(config.check().is_err());
+        config.kmer_len = 20;
+        assert!(config.check().is_ok());
+        assert_eq!(config.span(), 30);
+        config.ambiguity = AmbiguityPolicy::Expand(4);
+        assert!(config.check().is_err());
+        let mut config = Config::new(21);
+        config.ambiguity = AmbiguityPolicy::Substitute(b'U');
+        assert!(config.check().is_ok());
+        config.ambiguity = AmbiguityPolicy::Substitute(b'X');
+        assert!(config.check().is_err());
+    }
+
+    #[test]
+    pub fn test_count_kmers() {
+        let dir = std::env::temp_dir().join(format!("kmcrayfish-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fastq = dir.join("reads.fq");
+        let fasta = dir.join("reads.fa.gz");
+        let mut file = File::create(&fastq).unwrap();
+        writeln!(file, "@read1\nACGTTGCAnACGT\n+\n{}", "I".repeat(13)).unwrap();
+        let file = File::create(&fasta).unwrap();
+        let mut file = GzEncoder::new(file, flate2::Compression::default());
+        writeln!(file, ">read2\nTGC\nAACGT").unwrap();
+        file.finish().unwrap();
+
+        let table = count_kmers([&fastq, &fasta], Config::new(4)).unwrap();
+        let counts: Vec<(String, u64)> = table
+            .kmers::<KMer<4>>()
+            .map(|(kmer, count)| (kmer.to_string(), count))
+            .collect();
+        let expected = [
+            ("AACG", 2),
+            ("ACGT", 3),
+            ("CAAC", 2),
+            ("GCAA", 2),
+            ("TGCA", 2),
+        ];
+        let expected: Vec<(String, u64)> = expected
+            .iter()
+            .map(|&(kmer, count)| (kmer.to_string(), count))
+            .collect();
+        assert_eq!(counts, expected);
+
+        let tsv: String = counts
+            .iter()
+            .map(|(kmer, count)| format!("{}\t{}\n", kmer, count))
+            .collect();
+        let dump = dir.join("counts.tsv.zst");
+        table.dump(&dump).unwrap();
+        let mut dumped = String::new();
+        input::open(&dump)
+            .unwrap()
+            .read_to_string(&mut dumped)
+            .unwrap();
+        assert_eq!(dumped, tsv);
+        let part = part_path(dir.join("counts.tsv.zst"), 2);
+        assert_eq!(part, dir.join("counts.tsv.2.zst"));
+        table.dump(&part).unwrap();
+        let mut dumped = String::new();
+        input::open(&part)
+            .unwrap()
+            .read_to_string(&mut dumped)
+            .unwrap();
+        assert_eq!(dumped, tsv);
+        assert_eq!(part_path("counts.tsv", 0), Path::new("counts.tsv.0"));
+        table.dump(dir.join("counts.tsv")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("counts.tsv")).unwrap(),
+            tsv
+        );
+
+        assert!(count_kmers([&fasta], Config::new(200)).is_err());
+        assert!(count_kmers([dir.join("missing.fa")], Config::new(4)).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_count_chunked() {
+        let seq = crate::testutil::random_seq(3 * crate::reader::FASTA_CHUNK_LEN);
+        let mut fasta = b">long\n".to_vec();
+        for line in seq.chunks(80) {
+            fasta.extend_from_slice(line);
+            fasta.push(b'\n');
+        }
+        let mut config = Config::new(20);
+        config.sampling = Some(Sampling::Randstrobe(5, 40));
+        let mut splitter = ReadSplitter::new(config.clone());
+        let mut words = vec![];
+        splitter.kmers(&seq, |kmer| words.extend_from_slice(kmer));
+        let whole = CountTable::from_words(20, &mut words);
+
+        let mut reads = 0;
+        let mut words = vec![];
+        for read in config.seq_reader(&fasta[..]).unwrap() {
+            reads += 1;
+            splitter.kmers(&read.unwrap(), |kmer| words.extend_from_slice(kmer));
+        }
+        assert!(reads > 1);
+        let chunked = CountTable::from_words(20, &mut words);
+        assert!(whole.total() > 0);
+        assert!(whole.iter().eq(chunked.iter()));
+    }
+
+    #[test]
+    pub fn test_share_reads() {
+        let mut fastq: String = (0..10)
+            .map(|i| format!("@r{}\nACGTA\n+\nIIIII\n", i))
+            .collect();
+        fastq.push_str("@r10\nACGTA\nIIIII\n");
+        let mut gz = GzEncoder::new(vec![], flate2::Compression::default());
+        gz.write_all(fastq.as_bytes()).unwrap();
+        let gz = gz.finish().unwrap();
+
+        let config = Config::new(4);
+        let mut reads = 0;
+        for part in 0..3 {
+            let share: Vec<_> = share_reads(&config, &gz, part, 3).unwrap().collect();
+            assert!(share.last().unwrap().is_err());
+            reads += share.iter().filter(|read| read.is_ok()).count();
+        }
+        assert_eq!(reads, 10);
+
+        let wrapped = "@r1\nACG\nTA\n+\nIIIII\n".repeat(10);
+        let share: Vec<_> = share_reads(&config, wrapped.as_bytes(), 0, 1)
+            .unwrap()
+            .collect();
+        assert!(share[0].is_err());
+        assert!(share_reads(&config, b"", 0, 2).unwrap().next().is_none());
+    }
+}
@@ -0,0 +1,338 @@
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+
+use crate::reader;
+use crate::reader::find_record_start;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0];
+// gzip header up to the BSIZE of the BC extra subfield
+const BGZF_HEADER_LEN: usize = 18;
+
+/// How input is compressed, told by its magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Plain,
+    Gzip,
+    /// Gzip in independent blocks of at most 64 KiB, as written by bgzip.
+    Bgzf,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// Compression of input starting with `head`.
+    pub fn detect(head: &[u8]) -> Self {
+        if is_bgzf_block(head) {
+            Compression::Bgzf
+        } else if head.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if head.starts_with(&XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::Plain
+        }
+    }
+}
+
+// a gzip member with the FEXTRA flag whose first subfield is BC
+fn is_bgzf_block(head: &[u8]) -> bool {
+    head.len() >= BGZF_HEADER_LEN
+        && head.starts_with(&GZIP_MAGIC)
+        && head[3] & 4 != 0
+        && head[12..16] == [b'B', b'C', 2, 0]
+}
+
+/// Reads `reader` decompressed, whatever the compression. Concatenated
+/// streams read as one.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        Compression::Plain => Box::new(reader),
+        Compression::Gzip | Compression::Bgzf => {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+        }
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))),
+    })
+}
+
+/// Opens the file at `path` for reading, decompressed.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn BufRead>> {
+    decompress(BufReader::new(File::open(path)?))
+}
+
+/// Splits BGZF input into its blocks, by the sizes in their headers.
+pub fn bgzf_blocks(data: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let mut blocks = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let rest = &data[pos..];
+        if !is_bgzf_block(rest) {
+            return Err(invalid_block(pos));
+        }
+        let len = u16::from_le_bytes([rest[16], rest[17]]) as usize + 1;
+        if len > rest.len() {
+            return Err(invalid_block(pos));
+        }
+        blocks.push(&rest[..len]);
+        pos += len;
+    }
+    Ok(blocks)
+}
+
+fn invalid_block(pos: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid BGZF block at byte {}", pos),
+    )
+}
+
+fn decompress_block(block: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    GzDecoder::new(block).read_to_end(out)?;
+    Ok(())
+}
+
+/// The `part`th of `parts` shares of the records in the BGZF input `data`,
+/// decompressed a block at a time as it is read. The shares split at about
+/// equal compressed sizes, each decompressing its own blocks and reading on
+/// only to finish its last record, which `reader::split_input` does for
+/// plain input.
+pub fn bgzf_share(data: &[u8], part: usize, parts: usize) -> io::Result<BgzfShare<'_>> {
+    assert!(part < parts);
+    let blocks = bgzf_blocks(data)?;
+    let mut head = vec![];
+    if let Some(block) = blocks.first() {
+        decompress_block(block, &mut head)?;
+    }
+    let fastq = reader::is_fastq(&head);
+
+    // block the share of part `i` starts at
+    let first_block = |i: usize| {
+        if i == parts {
+            return blocks.len();
+        }
+        let from = data.len() / parts * i;
+        let mut offset = 0;
+        blocks
+            .iter()
+            .position(|block| {
+                offset += block.len();
+                offset - block.len() >= from
+            })
+            .unwrap_or(blocks.len())
+    };
+    let (first, last) = (first_block(part), first_block(part + 1));
+    let start = if part == 0 {
+        0
+    } else {
+        split_after(&blocks[first..], fastq)?
+    };
+    Ok(BgzfShare {
+        blocks,
+        fastq,
+        next: first,
+        last: if part + 1 == parts { None } else { Some(last) },
+        start,
+        end: usize::MAX,
+        offset: 0,
+        buf: vec![],
+        pos: 0,
+    })
+}
+
+// Shares split at the first record after the first line break in the first
+// block of the next part, so both parts find the same record although
+// neither has the bytes before the block. Where that is in the bytes
+// `blocks` decompress to.
+fn split_after(blocks: &[&[u8]], fastq: bool) -> io::Result<usize> {
+    let mut out = vec![];
+    let mut next = 0;
+    loop {
+        let start = find_record_start(&out, 1, fastq);
+        // a FASTQ record is only known by its first four lines
+        let lines = memchr::memchr_iter(b'\n', &out[start..]).take(4).count();
+        let known = start < out.len() && (!fastq || lines == 4);
+        if known || next == blocks.len() {
+            return Ok(start);
+        }
+        decompress_block(blocks[next], &mut out)?;
+        next += 1;
+    }
+}
+
+/// A share of BGZF input, see `bgzf_share`.
+pub struct BgzfShare<'a> {
+    blocks: Vec<&'a [u8]>,
+    fastq: bool,
+    // the block to decompress next, and the first of the next share
+    next: usize,
+    last: Option<usize>,
+    // the share's bytes, counted from its first block
+    start: usize,
+    end: usize,
+    // where `buf` starts, and what of it has been read
+    offset: usize,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> Read for BgzfShare<'a> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let buf = self.fill_buf()?;
+        let len = buf.len().min(out.len());
+        out[..len].copy_from_slice(&buf[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<'a> BufRead for BgzfShare<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.buf.len() {
+            self.offset += self.buf.len();
+            self.buf.clear();
+            self.pos = 0;
+            if self.next == self.blocks.len() || self.offset >= self.end {
+                break;
+            }
+            if Some(self.next) == self.last {
+                self.end = self.offset + split_after(&self.blocks[self.next..], self.fastq)?;
+            }
+            decompress_block(self.blocks[self.next], &mut self.buf)?;
+            self.next += 1;
+            // past the end, `offset` stops counting, the share being read
+            self.buf.truncate(self.end.saturating_sub(self.offset));
+            self.pos = self.start.saturating_sub(self.offset).min(self.buf.len());
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::SeqReader;
+    use flate2::write::DeflateEncoder;
+    use flate2::write::GzEncoder;
+    use flate2::Crc;
+    use std::io::Write;
+    use xz2::write::XzEncoder;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // BGZF of `data` in blocks of `block_len` bytes, and the empty end block
+    fn bgzf(data: &[u8], block_len: usize) -> Vec<u8> {
+        let mut out = vec![];
+        for chunk in data.chunks(block_len).chain(std::iter::once(&[][..])) {
+            let mut encoder = DeflateEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(chunk).unwrap();
+            let deflated = encoder.finish().unwrap();
+            let mut crc = Crc::new();
+            crc.update(chunk);
+            let bsize = (BGZF_HEADER_LEN + deflated.len() + 8 - 1) as u16;
+            out.extend_from_slice(&[0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0]);
+            out.extend_from_slice(&[b'B', b'C', 2, 0]);
+            out.extend_from_slice(&bsize.to_le_bytes());
+            out.extend_from_slice(&deflated);
+            out.extend_from_slice(&crc.sum().to_le_bytes());
+            out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        }
+        out
+    }
+
+    fn read_all(mut reader: impl BufRead) -> Vec<u8> {
+        let mut out = vec![];
+        reader.read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    pub fn test_decompress() {
+        let data = b">chr1\nACGT\n>chr2\nGGCC\n".repeat(100);
+        assert_eq!(Compression::detect(&data), Compression::Plain);
+        assert_eq!(read_all(decompress(&data[..]).unwrap()), data);
+        let gz = [gzip(&data[..1000]), gzip(&data[1000..])].concat();
+        assert_eq!(Compression::detect(&gz), Compression::Gzip);
+        assert_eq!(read_all(decompress(&gz[..]).unwrap()), data);
+        let bgz = bgzf(&data, 300);
+        assert_eq!(Compression::detect(&bgz), Compression::Bgzf);
+        assert_eq!(bgzf_blocks(&bgz).unwrap().len(), 9);
+        assert_eq!(read_all(decompress(&bgz[..]).unwrap()), data);
+        assert!(bgzf_blocks(&bgz[..bgz.len() - 1]).is_err());
+        assert!(read_all(decompress(&b""[..]).unwrap()).is_empty());
+        let zst = [
+            zstd::encode_all(&data[..1000], 0).unwrap(),
+            zstd::encode_all(&data[1000..], 0).unwrap(),
+        ]
+        .concat();
+        assert_eq!(Compression::detect(&zst), Compression::Zstd);
+        assert_eq!(read_all(decompress(&zst[..]).unwrap()), data);
+        let mut xz = vec![];
+        for part in [&data[..1000], &data[1000..]] {
+            let mut encoder = XzEncoder::new(vec![], 6);
+            encoder.write_all(part).unwrap();
+            xz.extend_from_slice(&encoder.finish().unwrap());
+        }
+        assert_eq!(Compression::detect(&xz), Compression::Xz);
+        assert_eq!(read_all(decompress(&xz[..]).unwrap()), data);
+    }
+
+    #[test]
+    pub fn test_bgzf_share() {
+        // qualities starting with '@' and '+' look like headers and separators
+        let fastq: String = (0..200)
+            .map(|i| format!("@r{}\nACGTA\n+\n{}IIII\n", i, ["@", "+", "I"][i % 3]))
+            .collect();
+        let fasta: String = (0..200)
+            .map(|i| format!(">chr{}\nACGTACGTAC\nACG\n", i))
+            .collect();
+        for data in [fastq.into_bytes(), fasta.into_bytes()] {
+            for block_len in [7, 64, 1000, data.len()] {
+                let bgz = bgzf(&data, block_len);
+                for parts in 1..8 {
+                    let mut joined = vec![];
+                    let mut reads = 0;
+                    for part in 0..parts {
+                        let share = read_all(bgzf_share(&bgz, part, parts).unwrap());
+                        reads += SeqReader::new(&share[..])
+                            .unwrap()
+                            .map(Result::unwrap)
+                            .count();
+                        joined.extend_from_slice(&share);
+                    }
+                    assert_eq!(joined, data);
+                    assert_eq!(reads, 200);
+                }
+            }
+        }
+
+        // a share decompresses no block past the one its last record ends in
+        let data = b">chr\nACGTACGTAC\n".repeat(200);
+        let mut bgz = bgzf(&data, 100);
+        let blocks = bgzf_blocks(&bgz).unwrap();
+        let corrupt = bgz.len() - blocks[blocks.len() - 1].len() - 5;
+        bgz[corrupt] ^= 1;
+        assert!(!read_all(bgzf_share(&bgz, 0, 2).unwrap()).is_empty());
+        let mut share = bgzf_share(&bgz, 1, 2).unwrap();
+        assert!(share.read_to_end(&mut vec![]).is_err());
+    }
+}
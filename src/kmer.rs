@@ -5,27 +5,35 @@ use std::fmt::Formatter;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::marker::PhantomData;
-use std::mem::size_of;
+use std::ops::Range;
 
 pub extern crate voracious_radix_sort as radix;
 
 pub trait Alphabet {
-    // size_of_usize % unit_len must be 0
+    // unit_len * kmer_len must fit in the k-mer storage
     const UNIT_LEN: usize;
+    // whether the reverse complement is the same molecule, e.g. false for peptides
+    const HAS_COMPLEMENT: bool;
+    // set when complement(unit) == !unit, so a whole word can be complemented at once
+    const COMPLEMENT_IS_NOT: bool = false;
     fn to_unit(base: u8) -> Option<u8>;
     fn to_u8(unit: u8) -> Option<u8>;
+    fn complement(unit: u8) -> u8;
 }
 
 pub struct DNA {}
 
 impl Alphabet for DNA {
     const UNIT_LEN: usize = 2;
+    const HAS_COMPLEMENT: bool = true;
+    const COMPLEMENT_IS_NOT: bool = true;
     fn to_unit(base: u8) -> Option<u8> {
+        // lowercase is soft-masked sequence, see split::MaskPolicy
         Some(match base {
-            b'A' => 0b00,
-            b'T' => 0b11,
-            b'G' => 0b10,
-            b'C' => 0b01,
+            b'A' | b'a' => 0b00,
+            b'T' | b't' => 0b11,
+            b'G' | b'g' => 0b10,
+            b'C' | b'c' => 0b01,
             _ => return None,
         })
     }
@@ -38,18 +46,201 @@ impl Alphabet for DNA {
             _ => return None,
         })
     }
+    fn complement(unit: u8) -> u8 {
+        !unit & 0b11
+    }
+}
+
+/// Same encoding as `DNA`, but reads `U` as `T` so RNA and DNA inputs give
+/// identical k-mers. `T_LETTER` is written back for that unit, `U` by default.
+pub struct RNA<const T_LETTER: u8 = b'U'> {}
+
+impl<const T_LETTER: u8> Alphabet for RNA<T_LETTER> {
+    const UNIT_LEN: usize = DNA::UNIT_LEN;
+    const HAS_COMPLEMENT: bool = DNA::HAS_COMPLEMENT;
+    const COMPLEMENT_IS_NOT: bool = DNA::COMPLEMENT_IS_NOT;
+    fn to_unit(base: u8) -> Option<u8> {
+        match base {
+            b'U' | b'u' => DNA::to_unit(b'T'),
+            _ => DNA::to_unit(base),
+        }
+    }
+    fn to_u8(unit: u8) -> Option<u8> {
+        match DNA::to_u8(unit)? {
+            b'T' => Some(T_LETTER),
+            base => Some(base),
+        }
+    }
+    fn complement(unit: u8) -> u8 {
+        DNA::complement(unit)
+    }
+}
+
+/// The four nucleotides in two bits, complemented, as ntHash hashes them.
+pub trait Nucleotides: Alphabet {}
+
+impl Nucleotides for DNA {}
+
+impl<const T_LETTER: u8> Nucleotides for RNA<T_LETTER> {}
+
+pub struct Protein {}
+
+const AMINO_ACIDS: &[u8; 20] = b"ACDEFGHIKLMNPQRSTVWY";
+
+impl Alphabet for Protein {
+    const UNIT_LEN: usize = 5;
+    const HAS_COMPLEMENT: bool = false;
+    fn to_unit(base: u8) -> Option<u8> {
+        // units follow the letter order so k-mers sort lexicographically
+        Some(match base {
+            b'A' => 0,
+            b'C' => 1,
+            b'D' => 2,
+            b'E' => 3,
+            b'F' => 4,
+            b'G' => 5,
+            b'H' => 6,
+            b'I' => 7,
+            b'K' => 8,
+            b'L' => 9,
+            b'M' => 10,
+            b'N' => 11,
+            b'P' => 12,
+            b'Q' => 13,
+            b'R' => 14,
+            b'S' => 15,
+            b'T' => 16,
+            b'V' => 17,
+            b'W' => 18,
+            b'Y' => 19,
+            _ => return None,
+        })
+    }
+    fn to_u8(unit: u8) -> Option<u8> {
+        AMINO_ACIDS.get(unit as usize).copied()
+    }
+    fn complement(unit: u8) -> u8 {
+        unit
+    }
 }
 
 pub trait AbstractKMer
 where
-    Self: Sized + Ord + Copy + Hash,
+    Self: Sized + Ord + Copy + Hash + Default,
 {
+    type Alphabet: Alphabet;
     fn kmer_len() -> usize;
+    fn has_complement() -> bool;
     fn extend(&self, base: u8) -> Option<Self>;
-    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+    /// Shifts the complement of `base` in on the left. On the reverse
+    /// complement of a k-mer this gives the reverse complement of `extend(base)`.
+    fn extend_rc(&self, base: u8) -> Option<Self>;
+    /// Parses the first `kmer_len()` bytes, any bytes after them are ignored.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KmerParseError> {
+        if bytes.len() < Self::kmer_len() {
+            return Err(KmerParseError::TooShort {
+                len: bytes.len(),
+                kmer_len: Self::kmer_len(),
+            });
+        }
+        let mut bytes = bytes[..Self::kmer_len()].iter().enumerate();
+        bytes.try_fold(Self::default(), |kmer, (pos, &byte)| {
+            let unit =
+                Self::Alphabet::to_unit(byte).ok_or(KmerParseError::InvalidByte { byte, pos })?;
+            Ok(kmer.with_unit(pos, unit))
+        })
+    }
+    /// Writes the bases, which is how every k-mer type displays.
+    fn write_bases(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for at in 0..Self::kmer_len() {
+            write!(
+                f,
+                "{}",
+                Self::Alphabet::to_u8(self.unit(at)).unwrap() as char
+            )?;
+        }
+        Ok(())
+    }
+    /// Builds a k-mer from units packed right-aligned into a `u128`, the first
+    /// unit most significant. Bits above the k-mer are dropped, and k-mers
+    /// wider than 128 bits start with zero units.
+    fn from_u128(units: u128) -> Self;
+    /// Number of `u64` words in the raw representation places exchange.
+    const WORDS: usize;
+    /// The `at`-th raw word, the most significant first, so raw words
+    /// compare in order like the k-mers they come from.
+    fn word(&self, at: usize) -> u64;
+    /// Builds a k-mer back from the first `WORDS` raw words of `words`.
+    fn from_words(words: &[u64]) -> Self;
+    /// Appends the `WORDS` raw words of the k-mer to `words`.
+    fn to_words(&self, words: &mut Vec<u64>) {
+        words.extend((0..Self::WORDS).map(|at| self.word(at)))
+    }
+    /// The unit at position `at`, 0 being the first base.
+    fn unit(&self, at: usize) -> u8;
+    /// A copy with the unit at position `at` replaced by `unit`.
+    fn with_unit(&self, at: usize, unit: u8) -> Self;
+    /// Shifts `base` in on the left, dropping the last base.
+    fn prepend(&self, base: u8) -> Option<Self>;
+    /// The k-mers following this one in the de Bruijn graph, one for each
+    /// base of the alphabet in unit order.
+    fn successors(&self) -> impl Iterator<Item = Self> {
+        let kmer = *self;
+        (0..1u8 << Self::Alphabet::UNIT_LEN)
+            .filter_map(move |unit| kmer.extend(Self::Alphabet::to_u8(unit)?))
+    }
+    /// The k-mers preceding this one in the de Bruijn graph.
+    fn predecessors(&self) -> impl Iterator<Item = Self> {
+        let kmer = *self;
+        (0..1u8 << Self::Alphabet::UNIT_LEN)
+            .filter_map(move |unit| kmer.prepend(Self::Alphabet::to_u8(unit)?))
+    }
+    /// The bases at positions `range`, as a k-mer exactly that long.
+    fn sub_kmer<S>(&self, range: Range<usize>) -> S
+    where
+        S: AbstractKMer<Alphabet = Self::Alphabet>,
+    {
+        assert_eq!(range.len(), S::kmer_len(), "sub-k-mer length mismatch");
+        assert!(range.end <= Self::kmer_len());
+        range
+            .enumerate()
+            .fold(S::default(), |sub, (i, at)| sub.with_unit(i, self.unit(at)))
+    }
+    /// The first k-1 bases.
+    fn prefix<P>(&self) -> P
+    where
+        P: AbstractKMer<Alphabet = Self::Alphabet>,
+    {
+        self.sub_kmer(0..Self::kmer_len() - 1)
+    }
+    /// The last k-1 bases.
+    fn suffix<P>(&self) -> P
+    where
+        P: AbstractKMer<Alphabet = Self::Alphabet>,
+    {
+        self.sub_kmer(1..Self::kmer_len())
+    }
     fn reverse(&self) -> Self;
     fn complement(&self) -> Self;
+    /// `reverse` unit by unit, for alphabets the packed reversals do not cover.
+    fn reverse_units(&self) -> Self {
+        let k = Self::kmer_len();
+        (0..k).fold(Self::default(), |rev, at| {
+            rev.with_unit(k - 1 - at, self.unit(at))
+        })
+    }
+    /// `complement` unit by unit, for alphabets whose complement is not `!`.
+    fn complement_units(&self) -> Self {
+        (0..Self::kmer_len()).fold(*self, |comp, at| {
+            comp.with_unit(at, Self::Alphabet::complement(self.unit(at)))
+        })
+    }
+    /// The smaller of the k-mer and its reverse complement. Alphabets without
+    /// a complement have no second strand, so the k-mer is its own canonical.
     fn get_canonical(&self) -> Self {
+        if !Self::has_complement() {
+            return *self;
+        }
         let rc = self.reverse().complement();
         if self > &rc {
             rc
@@ -59,31 +250,449 @@ where
     }
 }
 
-pub struct KMeru64<A, const KMERLEN: usize>
+/// Why bytes could not be parsed as a k-mer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KmerParseError {
+    /// Fewer bytes than the k-mer length.
+    TooShort { len: usize, kmer_len: usize },
+    /// A byte the alphabet does not accept, at the given position.
+    InvalidByte { byte: u8, pos: usize },
+}
+
+impl fmt::Display for KmerParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            KmerParseError::TooShort { len, kmer_len } => {
+                write!(f, "got {} bases, a k-mer needs {}", len, kmer_len)
+            }
+            KmerParseError::InvalidByte { byte, pos } => write!(
+                f,
+                "invalid base '{}' at position {}",
+                (byte as char).escape_default(),
+                pos
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KmerParseError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+/// Iterates `(position, canonical k-mer, strand)` over a sequence, keeping the
+/// forward and reverse complement k-mers rolling so each base costs O(1).
+/// Bases the alphabet rejects restart the k-mer after them.
+pub struct CanonicalKmers<'a, K> {
+    seq: &'a [u8],
+    pos: usize,
+    run: usize, // valid bases read since the last restart
+    forward: K,
+    reverse: K,
+}
+
+impl<'a, K> CanonicalKmers<'a, K>
 where
-    A: Alphabet,
+    K: AbstractKMer,
 {
-    pub data: u64,
-    _mark: PhantomData<A>,
+    pub fn new(seq: &'a [u8]) -> Self {
+        CanonicalKmers {
+            seq,
+            pos: 0,
+            run: 0,
+            forward: K::default(),
+            reverse: K::default(),
+        }
+    }
 }
 
-impl<A, const N:usize> radix::Radixable<u64> for KMeru64<A, N> where A: Alphabet{
-    type Key = u64;
-    fn key(&self) -> Self::Key{
-        self.data
+impl<'a, K> Iterator for CanonicalKmers<'a, K>
+where
+    K: AbstractKMer,
+{
+    type Item = (usize, K, Strand);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.seq.len() {
+            let base = self.seq[self.pos];
+            self.pos += 1;
+            match self.forward.extend(base) {
+                Some(k) => self.forward = k,
+                None => {
+                    self.run = 0;
+                    continue;
+                }
+            }
+            self.run += 1;
+            if K::has_complement() {
+                // the base was valid for extend, so it is valid here too
+                self.reverse = self.reverse.extend_rc(base).unwrap();
+            }
+            if self.run >= K::kmer_len() {
+                let start = self.pos - K::kmer_len();
+                return Some(if !K::has_complement() || self.forward <= self.reverse {
+                    (start, self.forward, Strand::Forward)
+                } else {
+                    (start, self.reverse, Strand::Reverse)
+                });
+            }
+        }
+        None
     }
 }
-impl<A, const N: usize> Copy for KMeru64<A, N> where A: Alphabet {}
-impl<A, const N: usize> Clone for KMeru64<A, N>
+
+/// Runs `$body` with the const `$n` bound to a k-mer length known at runtime,
+/// any k from 1 to 127. Every k gets a monomorphized instance of the body;
+/// any other `$k` evaluates `$unsupported`.
+#[macro_export]
+macro_rules! dispatch_kmer_len {
+    ($k:expr, $n:ident => $body:expr, _ => $unsupported:expr) => {
+        $crate::dispatch_kmer_len!(@table $k, $n, $body, $unsupported;
+            1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+            17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+            33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48
+            49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64
+            65 66 67 68 69 70 71 72 73 74 75 76 77 78 79 80
+            81 82 83 84 85 86 87 88 89 90 91 92 93 94 95 96
+            97 98 99 100 101 102 103 104 105 106 107 108 109 110 111 112
+            113 114 115 116 117 118 119 120 121 122 123 124 125 126 127)
+    };
+    // Spaced seeds and sampling instantiate several iterators per k, so
+    // above 64 they only get SAMPLED_LONG_KMER_LENS.
+    (@sampled $k:expr, $n:ident => $body:expr, _ => $unsupported:expr) => {
+        $crate::dispatch_kmer_len!(@table $k, $n, $body, $unsupported;
+            1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+            17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+            33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48
+            49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64
+            71 81 91 101 111 121 127)
+    };
+    (@table $k:expr, $n:ident, $body:expr, $unsupported:expr; $($len:literal)*) => {
+        match $k {
+            $(
+                $len => {
+                    const $n: usize = $len;
+                    $body
+                }
+            )*
+            _ => $unsupported,
+        }
+    };
+}
+
+/// The k above 64 spaced seeds and sampling are dispatched for, the ones
+/// common in assembly; below they get every k.
+pub const SAMPLED_LONG_KMER_LENS: [usize; 7] = [71, 81, 91, 101, 111, 121, 127];
+
+pub fn kmer_len_supported(k: usize) -> bool {
+    crate::dispatch_kmer_len!(k, N => N == k, _ => false)
+}
+
+/// Whether spaced seeds and sampling are dispatched for `k`.
+pub fn sampled_kmer_len_supported(k: usize) -> bool {
+    crate::dispatch_kmer_len!(@sampled k, N => N == k, _ => false)
+}
+
+/// The narrowest k-mer type holding `N` units of an alphabet, so code generic
+/// over the k-mer type can be handed `KMerOf<A, N>` for a dispatched length.
+/// Implemented for k up to 128, which covers the lengths dispatched and the
+/// s-mers and strobes derived from them.
+pub trait KMerWidth<const N: usize>: Alphabet + Sized {
+    type KMer: AbstractKMer<Alphabet = Self>;
+}
+
+pub type KMerOf<A, const N: usize> = <A as KMerWidth<N>>::KMer;
+
+macro_rules! impl_kmer_width {
+    ($a:ty; u64: $($short:literal)*; u128: $($long:literal)*; arr: $($wide:literal)*) => {
+        $(impl KMerWidth<$short> for $a {
+            type KMer = KMeru64<$a, $short>;
+        })*
+        $(impl KMerWidth<$long> for $a {
+            type KMer = KMeru128<$a, $long>;
+        })*
+        $(impl KMerWidth<$wide> for $a {
+            type KMer = KMerArr<$a, $wide, { ($wide * <$a as Alphabet>::UNIT_LEN).div_ceil(64) }>;
+        })*
+    };
+    ($($a:ty),*; 2 bits) => {
+        $(impl_kmer_width!($a;
+            u64:
+                1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+                17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32;
+            u128:
+                33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48
+                49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64;
+            arr:
+                65 66 67 68 69 70 71 72 73 74 75 76 77 78 79 80
+                81 82 83 84 85 86 87 88 89 90 91 92 93 94 95 96
+                97 98 99 100 101 102 103 104 105 106 107 108 109 110 111 112
+                113 114 115 116 117 118 119 120 121 122 123 124 125 126 127 128);)*
+    };
+}
+
+impl_kmer_width!(DNA, RNA<b'T'>, RNA<b'U'>; 2 bits);
+impl_kmer_width!(Protein;
+    u64:
+        1 2 3 4 5 6 7 8 9 10 11 12;
+    u128:
+        13 14 15 16 17 18 19 20 21 22 23 24 25;
+    arr:
+        26 27 28 29 30 31 32 33 34 35 36 37 38 39 40 41
+        42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57
+        58 59 60 61 62 63 64 65 66 67 68 69 70 71 72 73
+        74 75 76 77 78 79 80 81 82 83 84 85 86 87 88 89
+        90 91 92 93 94 95 96 97 98 99 100 101 102 103 104 105
+        106 107 108 109 110 111 112 113 114 115 116 117 118 119 120 121
+        122 123 124 125 126 127 128);
+
+// Integer-backed k-mers: the units sit right-aligned in `data`, the first
+// base most significant, so comparing `data` compares the k-mers.
+macro_rules! impl_int_kmer {
+    ($(#[$attr:meta])* $name:ident, $int:ty, $reverse:ident) => {
+        $(#[$attr])*
+        pub struct $name<A, const KMERLEN: usize>
+        where
+            A: Alphabet,
+        {
+            pub data: $int,
+            _mark: PhantomData<A>,
+        }
+
+        impl<A, const N: usize> radix::Radixable<$int> for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            type Key = $int;
+            fn key(&self) -> Self::Key {
+                self.data
+            }
+        }
+
+        impl<A, const N: usize> Copy for $name<A, N> where A: Alphabet {}
+        impl<A, const N: usize> Clone for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<A, const N: usize> Hash for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.data.hash(state);
+            }
+        }
+
+        impl<A, const N: usize> Eq for $name<A, N> where A: Alphabet {}
+
+        impl<A, const N: usize> PartialEq for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.data == other.data
+            }
+        }
+
+        impl<A, const N: usize> Ord for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.data.cmp(&other.data)
+            }
+        }
+
+        impl<A, const N: usize> PartialOrd for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<A, const N: usize> Debug for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+                self.write_bases(f)
+            }
+        }
+
+        impl<A, const N: usize> fmt::Display for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                self.write_bases(f)
+            }
+        }
+
+        impl<A, const N: usize> std::str::FromStr for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            type Err = KmerParseError;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_bytes(s.as_bytes())
+            }
+        }
+
+        impl<A, const N: usize> Default for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+
+        impl<A, const N: usize> $name<A, N>
+        where
+            A: Alphabet,
+        {
+            pub fn new(data: $int) -> Self {
+                $name {
+                    data,
+                    _mark: PhantomData,
+                }
+            }
+
+            // units are counted from the first base of the k-mer, which sits
+            // right above the unused bits
+            fn unit_shift(at: usize) -> usize {
+                A::UNIT_LEN * (Self::kmer_len() - 1 - at)
+            }
+
+            fn get_unit(&self, at: usize) -> u8 {
+                debug_assert!(at < Self::kmer_len());
+                let ret = self.data >> Self::unit_shift(at);
+                (ret & ((1 << A::UNIT_LEN) - 1)) as u8
+            }
+
+            // mask of the bits the units use
+            fn used_mask() -> $int {
+                debug_assert!(A::UNIT_LEN * N <= <$int>::BITS as usize);
+                <$int>::MAX >> (<$int>::BITS as usize - A::UNIT_LEN * N)
+            }
+        }
+
+        impl<A, const N: usize> AbstractKMer for $name<A, N>
+        where
+            A: Alphabet,
+        {
+            type Alphabet = A;
+            fn extend(&self, base: u8) -> Option<Self> {
+                let unit = <$int>::from(A::to_unit(base)?);
+                Some(Self::new((self.data << A::UNIT_LEN | unit) & Self::used_mask()))
+            }
+            fn extend_rc(&self, base: u8) -> Option<Self> {
+                let unit = <$int>::from(A::complement(A::to_unit(base)?));
+                Some(Self::new(self.data >> A::UNIT_LEN | unit << Self::unit_shift(0)))
+            }
+            fn kmer_len() -> usize {
+                N
+            }
+            fn has_complement() -> bool {
+                A::HAS_COMPLEMENT
+            }
+            fn complement(&self) -> Self {
+                if !A::HAS_COMPLEMENT {
+                    return *self;
+                }
+                if !A::COMPLEMENT_IS_NOT {
+                    return self.complement_units();
+                }
+                Self::new(!self.data & Self::used_mask())
+            }
+            fn reverse(&self) -> Self {
+                if A::UNIT_LEN != 2 {
+                    return self.reverse_units();
+                }
+                Self::new($reverse(self.data) >> (<$int>::BITS as usize - A::UNIT_LEN * N))
+            }
+            fn from_u128(units: u128) -> Self {
+                // truncating to the storage, then to the k-mer
+                Self::new(units as $int & Self::used_mask())
+            }
+            const WORDS: usize = (<$int>::BITS / u64::BITS) as usize;
+            fn word(&self, at: usize) -> u64 {
+                (self.data >> (u64::BITS as usize * (Self::WORDS - 1 - at))) as u64
+            }
+            fn from_words(words: &[u64]) -> Self {
+                // a u64 has no bits above its one word to shift the next into
+                let data = words[..Self::WORDS].iter().fold(0, |data: $int, &word| {
+                    data.checked_shl(u64::BITS).unwrap_or(0) | <$int>::from(word)
+                });
+                Self::new(data)
+            }
+            fn unit(&self, at: usize) -> u8 {
+                self.get_unit(at)
+            }
+            fn with_unit(&self, at: usize, unit: u8) -> Self {
+                let diff = <$int>::from(self.get_unit(at) ^ unit);
+                Self::new(self.data ^ diff << Self::unit_shift(at))
+            }
+            fn prepend(&self, base: u8) -> Option<Self> {
+                let unit = <$int>::from(A::to_unit(base)?);
+                Some(Self::new(self.data >> A::UNIT_LEN | unit << Self::unit_shift(0)))
+            }
+            fn sub_kmer<S>(&self, range: Range<usize>) -> S
+            where
+                S: AbstractKMer<Alphabet = A>,
+            {
+                assert_eq!(range.len(), S::kmer_len(), "sub-k-mer length mismatch");
+                assert!(range.end <= N);
+                S::from_u128(u128::from(self.data >> Self::unit_shift(range.end - 1)))
+            }
+        }
+    };
+}
+
+impl_int_kmer!(
+    /// K-mer packed into a `u64`, up to 32 bases of a 2-bit alphabet.
+    KMeru64,
+    u64,
+    reverse_u64_pack_2
+);
+impl_int_kmer!(
+    /// K-mer packed into a `u128`, up to 64 bases of a 2-bit alphabet.
+    KMeru128,
+    u128,
+    reverse_u128_pack_2
+);
+
+/// K-mer stored across `WORDS` u64 words for k beyond what fits in a u128.
+/// `data[0]` holds the most significant word so that ordering the array
+/// orders the k-mers.
+pub struct KMerArr<A, const KMERLEN: usize, const WORDS: usize>
+where
+    A: Alphabet,
+{
+    pub data: [u64; WORDS],
+    _mark: PhantomData<A>,
+}
+
+impl<A, const N: usize, const W: usize> Copy for KMerArr<A, N, W> where A: Alphabet {}
+impl<A, const N: usize, const W: usize> Clone for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
     fn clone(&self) -> Self {
-        Self::new(self.data)
+        *self
     }
 }
 
-impl<A, const N: usize> Hash for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> Hash for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
@@ -92,9 +701,9 @@ where
     }
 }
 
-impl<A, const N: usize> Eq for KMeru64<A, N> where A: Alphabet {}
+impl<A, const N: usize, const W: usize> Eq for KMerArr<A, N, W> where A: Alphabet {}
 
-impl<A, const N: usize> PartialEq for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> PartialEq for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
@@ -103,7 +712,7 @@ where
     }
 }
 
-impl<A, const N: usize> Ord for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> Ord for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
@@ -112,146 +721,497 @@ where
     }
 }
 
-impl<A, const N: usize> PartialOrd for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> PartialOrd for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.data.partial_cmp(&other.data)
+        Some(self.cmp(other))
     }
 }
 
-impl<A, const N: usize> Debug for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> Debug for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
-        f.write_str(&self.to_string())
+        self.write_bases(f)
     }
 }
 
-impl<A, const N: usize> Default for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> Default for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
     fn default() -> Self {
-        Self::new(0)
+        Self::new([0; W])
     }
 }
 
-impl<A, const N: usize> KMeru64<A, N>
+impl<A, const N: usize, const W: usize> KMerArr<A, N, W>
 where
     A: Alphabet,
 {
-    pub fn new(data: u64) -> Self {
-        KMeru64 {
+    pub fn new(data: [u64; W]) -> Self {
+        KMerArr {
             data,
             _mark: PhantomData,
         }
     }
+
+    // word index and bit shift of a unit, counted from the first base of the
+    // k-mer. A unit may straddle into the next more significant word.
+    fn unit_pos(at: usize) -> (usize, usize) {
+        let shift = A::UNIT_LEN * (Self::kmer_len() - 1 - at);
+        (W - 1 - shift / Self::word_len(), shift % Self::word_len())
+    }
+
     fn set_unit(&mut self, at: usize, unit: u8) {
         // this won't rewrite if unit is set
         let unit = unit as u64;
-        debug_assert!(at < Self::unit_num());
+        debug_assert!(at < Self::kmer_len());
         debug_assert!(unit < 1 << A::UNIT_LEN);
-        self.data |= unit << (Self::data_len() - A::UNIT_LEN * (at + 1))
+        let (word, shift) = Self::unit_pos(at);
+        self.data[word] |= unit << shift;
+        if shift + A::UNIT_LEN > Self::word_len() {
+            self.data[word - 1] |= unit >> (Self::word_len() - shift);
+        }
     }
 
     fn get_unit(&self, at: usize) -> u8 {
-        debug_assert!(at < size_of::<u64>() * 8 / A::UNIT_LEN);
-        let mut ret = self.data >> (Self::data_len() - A::UNIT_LEN * (at + 1));
+        debug_assert!(at < Self::kmer_len());
+        let (word, shift) = Self::unit_pos(at);
+        let mut ret = self.data[word] >> shift;
+        if shift + A::UNIT_LEN > Self::word_len() {
+            ret |= self.data[word - 1] << (Self::word_len() - shift);
+        }
         ret &= (1 << A::UNIT_LEN) - 1;
         ret as u8
     }
 
+    // shift the whole array towards the least significant end
+    fn shift_right(&mut self, bits: usize) {
+        let word_len = Self::word_len();
+        let words = bits / word_len;
+        let rem = bits % word_len;
+        for i in (0..W).rev() {
+            self.data[i] = if i < words {
+                0
+            } else {
+                let src = i - words;
+                let mut v = self.data[src] >> rem;
+                if rem > 0 && src > 0 {
+                    v |= self.data[src - 1] << (word_len - rem);
+                }
+                v
+            };
+        }
+    }
+
+    // clear the bits above used_bits
+    fn mask_unused(&mut self) {
+        let word_len = Self::word_len();
+        let unused = Self::unused_bits();
+        for w in self.data.iter_mut().take(unused / word_len) {
+            *w = 0;
+        }
+        if unused / word_len < W {
+            self.data[unused / word_len] &= u64::MAX >> (unused % word_len);
+        }
+    }
+
     fn unused_bits() -> usize {
-        size_of::<u64>() * 8 - Self::used_bits()
+        Self::data_len() - Self::used_bits()
     }
 
     fn used_bits() -> usize {
         A::UNIT_LEN * Self::kmer_len()
     }
 
-    fn data_len() -> usize {
-        size_of::<u64>() * 8
+    fn word_len() -> usize {
+        u64::BITS as usize
     }
 
-    fn unit_num() -> usize {
-        Self::data_len() / A::UNIT_LEN
+    fn data_len() -> usize {
+        Self::word_len() * W
     }
 }
 
-impl<A, const N: usize> AbstractKMer for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> AbstractKMer for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
+    type Alphabet = A;
     fn extend(&self, base: u8) -> Option<Self> {
         let mut next = *self;
         let unit = A::to_unit(base)? as u64;
-        next.data = self.data << A::UNIT_LEN | unit;
-        next.data &= (u64::MAX) >> Self::unused_bits();
+        let carry_shift = Self::word_len() - A::UNIT_LEN;
+        for i in 0..W - 1 {
+            next.data[i] = self.data[i] << A::UNIT_LEN | self.data[i + 1] >> carry_shift;
+        }
+        next.data[W - 1] = self.data[W - 1] << A::UNIT_LEN | unit;
+        next.mask_unused();
+        Some(next)
+    }
+    fn extend_rc(&self, base: u8) -> Option<Self> {
+        let mut next = *self;
+        let unit = A::complement(A::to_unit(base)?);
+        next.shift_right(A::UNIT_LEN);
+        next.set_unit(0, unit);
         Some(next)
     }
     fn kmer_len() -> usize {
         N
     }
+    fn has_complement() -> bool {
+        A::HAS_COMPLEMENT
+    }
     fn complement(&self) -> Self {
-        let data = !self.data & u64::MAX >> Self::unused_bits();
-        Self::new(data)
+        if !A::HAS_COMPLEMENT {
+            return *self;
+        }
+        if !A::COMPLEMENT_IS_NOT {
+            return self.complement_units();
+        }
+        let mut next = *self;
+        for w in next.data.iter_mut() {
+            *w = !*w;
+        }
+        next.mask_unused();
+        next
     }
     fn reverse(&self) -> Self {
-        #[cfg(not(target_feature = "sse"))]
-        compile_error!("should support ssse3");
-        #[cfg(target_feature = "sse")]
-        let data = ssse3::reverse_u64_pack_2(self.data) >> Self::unused_bits();
-        Self::new(data)
+        if A::UNIT_LEN != 2 {
+            return self.reverse_units();
+        }
+        let mut next = *self;
+        reverse_words_pack_2(&self.data, &mut next.data);
+        next.shift_right(Self::unused_bits());
+        next
     }
 
-    fn from_bytes(s: &[u8]) -> Option<Self> {
-        let mut kmer = Self::default();
-
-        if s.len() < Self::kmer_len() {
-            return None;
+    fn from_u128(units: u128) -> Self {
+        let mut data = [0; W];
+        data[W - 1] = units as u64;
+        if W > 1 {
+            data[W - 2] = (units >> Self::word_len()) as u64;
         }
-        let mut iter = s.iter().take(Self::kmer_len()).enumerate();
-        while let Some((i, c)) = iter.next() {
-            kmer.set_unit(i, A::to_unit(*c)?)
+        let mut kmer = Self::new(data);
+        kmer.mask_unused();
+        kmer
+    }
+    const WORDS: usize = W;
+    fn word(&self, at: usize) -> u64 {
+        self.data[at]
+    }
+    fn from_words(words: &[u64]) -> Self {
+        let mut data = [0; W];
+        data.copy_from_slice(&words[..W]);
+        Self::new(data)
+    }
+    fn unit(&self, at: usize) -> u8 {
+        self.get_unit(at)
+    }
+    fn with_unit(&self, at: usize, unit: u8) -> Self {
+        let mut next = *self;
+        let diff = (self.get_unit(at) ^ unit) as u64;
+        let (word, shift) = Self::unit_pos(at);
+        next.data[word] ^= diff << shift;
+        if shift + A::UNIT_LEN > Self::word_len() {
+            next.data[word - 1] ^= diff >> (Self::word_len() - shift);
         }
-        kmer.data >>= Self::unused_bits();
-        Some(kmer)
+        next
+    }
+    fn prepend(&self, base: u8) -> Option<Self> {
+        let mut next = *self;
+        let unit = A::to_unit(base)?;
+        next.shift_right(A::UNIT_LEN);
+        next.set_unit(0, unit);
+        Some(next)
     }
 }
 
-impl<A, const N: usize> std::str::FromStr for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> std::str::FromStr for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
-    type Err = ();
+    type Err = KmerParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::from_bytes(s.as_bytes()).ok_or(())
+        Self::from_bytes(s.as_bytes())
     }
 }
 
-impl<A, const N: usize> std::string::ToString for KMeru64<A, N>
+impl<A, const N: usize, const W: usize> fmt::Display for KMerArr<A, N, W>
 where
     A: Alphabet,
 {
-    fn to_string(&self) -> String {
-        let mut s = vec![];
-        for i in Self::unused_bits() / A::UNIT_LEN..size_of::<u64>() * 8 / A::UNIT_LEN {
-            s.push(A::to_u8(self.get_unit(i)).unwrap())
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_bases(f)
+    }
+}
+
+/// Hashes k-mers to 64 bits, e.g. to pick the place a k-mer is counted on.
+/// Implementations are deterministic, so every place and every run agree.
+pub trait KmerHasher<K> {
+    fn hash(&self, kmer: &K) -> u64;
+}
+
+/// Thomas Wang's 64-bit mix, a bijection on `u64`.
+pub fn mix64(key: u64) -> u64 {
+    let mut key = (!key).wrapping_add(key << 21);
+    key ^= key >> 24;
+    key = key.wrapping_mul(265);
+    key ^= key >> 14;
+    key = key.wrapping_mul(21);
+    key ^= key >> 28;
+    key.wrapping_add(key << 31)
+}
+
+/// Inverse of `mix64`.
+pub fn unmix64(hash: u64) -> u64 {
+    // undo key + (key << 31)
+    let tmp = hash.wrapping_sub(hash << 31);
+    let mut key = hash.wrapping_sub(tmp << 31);
+    // undo key ^ (key >> 28)
+    let tmp = key ^ key >> 28;
+    key ^= tmp >> 28;
+    // undo key * 21, key * 265 and the shifts between them
+    key = key.wrapping_mul(14933078535860113213);
+    let mut tmp = key ^ key >> 14;
+    tmp = key ^ tmp >> 14;
+    tmp = key ^ tmp >> 14;
+    key ^= tmp >> 14;
+    key = key.wrapping_mul(15244667743933553977);
+    let tmp = key ^ key >> 24;
+    key ^= tmp >> 24;
+    // undo (!key) + (key << 21)
+    let mut tmp = !key;
+    tmp = !(key.wrapping_sub(tmp << 21));
+    tmp = !(key.wrapping_sub(tmp << 21));
+    !(key.wrapping_sub(tmp << 21))
+}
+
+/// `mix64` of the k-mer bits xor a seed. Different seeds give independent
+/// looking hashes, and `invert` gets the k-mer back from its hash. K-mers
+/// of several raw words mix them in one by one, those are not invertible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InvertibleHash {
+    seed: u64,
+}
+
+impl InvertibleHash {
+    pub const fn new(seed: u64) -> Self {
+        InvertibleHash { seed }
+    }
+
+    /// Hash of a k-mer given by its raw words, see `AbstractKMer::to_words`.
+    pub fn hash_words(&self, words: &[u64]) -> u64 {
+        words
+            .iter()
+            .fold(self.seed, |hash, &word| mix64(word ^ hash))
+    }
+
+    pub fn invert<A, const N: usize>(&self, hash: u64) -> KMeru64<A, N>
+    where
+        A: Alphabet,
+    {
+        KMeru64::new(unmix64(hash) ^ self.seed)
+    }
+}
+
+impl<K> KmerHasher<K> for InvertibleHash
+where
+    K: AbstractKMer,
+{
+    fn hash(&self, kmer: &K) -> u64 {
+        (0..K::WORDS).fold(self.seed, |hash, at| mix64(kmer.word(at) ^ hash))
+    }
+}
+
+// ntHash seeds by 2-bit unit, which is A C G T in that order for DNA
+const NTHASH_SEEDS: [u64; 4] = [
+    0x3c8bfbb395c60474,
+    0x3193c18562a02b4c,
+    0x20323ed082572324,
+    0x295549f54be24456,
+];
+
+fn nthash_seed(unit: u8) -> u64 {
+    NTHASH_SEEDS[unit as usize]
+}
+
+/// ntHash of canonical nucleotide k-mers of any width: the smaller of the
+/// hashes of the two strands, so both strands hash alike. `NtHashes` computes
+/// the same hashes rolling along a sequence.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NtHash;
+
+impl<K> KmerHasher<K> for NtHash
+where
+    K: AbstractKMer,
+    K::Alphabet: Nucleotides,
+{
+    fn hash(&self, kmer: &K) -> u64 {
+        let k = K::kmer_len();
+        let (mut forward, mut reverse) = (0u64, 0u64);
+        for i in 0..k {
+            let unit = kmer.unit(i);
+            forward ^= nthash_seed(unit).rotate_left((k - 1 - i) as u32);
+            reverse ^= nthash_seed(K::Alphabet::complement(unit)).rotate_left(i as u32);
         }
-        String::from_utf8(s).unwrap()
+        forward.min(reverse)
     }
 }
 
-#[cfg(target_feature = "sse")]
+/// Iterates `(position, canonical ntHash)` over the k-mers of a sequence, each
+/// base costs O(1). Bases the alphabet rejects restart the k-mer after them.
+pub struct NtHashes<'a, K> {
+    seq: &'a [u8],
+    pos: usize,
+    run: usize,
+    forward: u64,
+    reverse: u64,
+    _mark: PhantomData<K>,
+}
+
+impl<'a, K> NtHashes<'a, K>
+where
+    K: AbstractKMer,
+    K::Alphabet: Nucleotides,
+{
+    pub fn new(seq: &'a [u8]) -> Self {
+        NtHashes {
+            seq,
+            pos: 0,
+            run: 0,
+            forward: 0,
+            reverse: 0,
+            _mark: PhantomData,
+        }
+    }
+}
+
+impl<'a, K> Iterator for NtHashes<'a, K>
+where
+    K: AbstractKMer,
+    K::Alphabet: Nucleotides,
+{
+    type Item = (usize, u64);
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = K::kmer_len();
+        while self.pos < self.seq.len() {
+            let unit = K::Alphabet::to_unit(self.seq[self.pos]);
+            self.pos += 1;
+            let unit = match unit {
+                Some(unit) => unit,
+                None => {
+                    self.run = 0;
+                    self.forward = 0;
+                    self.reverse = 0;
+                    continue;
+                }
+            };
+            self.run += 1;
+            let seed = nthash_seed(unit);
+            let rc_seed = nthash_seed(K::Alphabet::complement(unit));
+            self.forward = self.forward.rotate_left(1) ^ seed;
+            self.reverse = self.reverse.rotate_right(1) ^ rc_seed.rotate_left(k as u32 - 1);
+            if self.run > k {
+                // the base leaving the k-mer is valid, it was read in this run
+                let out = K::Alphabet::to_unit(self.seq[self.pos - 1 - k]).unwrap();
+                let rc_out = K::Alphabet::complement(out);
+                self.forward ^= nthash_seed(out).rotate_left(k as u32);
+                self.reverse ^= nthash_seed(rc_out).rotate_right(1);
+            }
+            if self.run >= k {
+                return Some((self.pos - k, self.forward.min(self.reverse)));
+            }
+        }
+        None
+    }
+}
+
+// Reverse the order of the 2-bit units in a word. The SIMD versions are
+// picked by the features of the running CPU; all of them must agree with the
+// scalar one, which is used everywhere else.
+fn reverse_u64_pack_2(data: u64) -> u64 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { ssse3::reverse_u64_pack_2(data) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { neon::reverse_u64_pack_2(data) };
+        }
+    }
+    scalar::reverse_u64_pack_2(data)
+}
+
+fn reverse_u128_pack_2(data: u128) -> u128 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { ssse3::reverse_u128_pack_2(data) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { neon::reverse_u128_pack_2(data) };
+        }
+    }
+    scalar::reverse_u128_pack_2(data)
+}
+
+// dst[i] = reverse(src[len - 1 - i]), i.e. the units of the whole word array reversed
+fn reverse_words_pack_2(src: &[u64], dst: &mut [u64]) {
+    debug_assert_eq!(src.len(), dst.len());
+    let len = src.len();
+    let mut done = 0;
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            while done + 4 <= len {
+                let words = &src[len - done - 4..len - done];
+                unsafe { avx2::reverse_u64x4_pack_2(words, &mut dst[done..done + 4]) };
+                done += 4;
+            }
+        }
+    }
+    for i in done..len {
+        dst[i] = reverse_u64_pack_2(src[len - 1 - i]);
+    }
+}
+
+mod scalar {
+    const PAIRS_64: u64 = 0x3333_3333_3333_3333;
+    const NIBBLES_64: u64 = 0x0F0F_0F0F_0F0F_0F0F;
+
+    pub fn reverse_u64_pack_2(mut data: u64) -> u64 {
+        // swap the units inside each byte, then the bytes
+        data = (data >> 2 & PAIRS_64) | (data & PAIRS_64) << 2;
+        data = (data >> 4 & NIBBLES_64) | (data & NIBBLES_64) << 4;
+        data.swap_bytes()
+    }
+
+    pub fn reverse_u128_pack_2(data: u128) -> u128 {
+        let hi = reverse_u64_pack_2((data >> 64) as u64) as u128;
+        let lo = reverse_u64_pack_2(data as u64) as u128;
+        lo << 64 | hi
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod ssse3 {
     #[cfg(target_arch = "x86")]
     use std::arch::x86::*;
     #[cfg(target_arch = "x86_64")]
     use std::arch::x86_64::*;
 
+    #[target_feature(enable = "ssse3")]
     unsafe fn reverse_m128i_pack_2(mut v: __m128i) -> __m128i {
         // from https://github.com/ParBLiSS/kmerind/blob/0062fe91fdeef66fce4d1e897c15318241130277/src/common/test/kmer_reverse_helper.hpp#L269
         // reverse byte
@@ -292,27 +1252,104 @@ mod ssse3 {
         _mm_or_si128(slo, shi)
     }
 
-    pub fn reverse_u64_pack_2(data: u64) -> u64 {
-        unsafe {
-            let v = _mm_set_epi64x(data as i64, data as i64);
-            _mm_extract_epi64(reverse_m128i_pack_2(v), 1) as u64
-        }
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn reverse_u64_pack_2(data: u64) -> u64 {
+        let v = _mm_set_epi64x(data as i64, data as i64);
+        let mut out = [0u64; 2];
+        _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, reverse_m128i_pack_2(v));
+        out[1]
     }
 
-    #[cfg(test)]
-    mod test {
-        use super::*;
-        #[test]
-        pub fn test_reverse_u64_pack_2() {
-            let data: u64 = 0x0123456789ABCDEF;
-            assert_eq!(reverse_u64_pack_2(reverse_u64_pack_2(data)), data)
-        }
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn reverse_u128_pack_2(data: u128) -> u128 {
+        let v = _mm_set_epi64x((data >> 64) as i64, data as i64);
+        let mut out = [0u64; 2];
+        _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, reverse_m128i_pack_2(v));
+        (out[1] as u128) << 64 | out[0] as u128
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    // same steps as ssse3::reverse_m128i_pack_2, on four words at once
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn reverse_u64x4_pack_2(src: &[u64], dst: &mut [u64]) {
+        debug_assert!(src.len() == 4 && dst.len() == 4);
+        let mut v = _mm256_loadu_si256(src.as_ptr() as *const __m256i);
+        // reverse the bytes of each word, then the words
+        v = _mm256_shuffle_epi8(
+            v,
+            _mm256_setr_epi8(
+                7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15,
+                14, 13, 12, 11, 10, 9, 8,
+            ),
+        );
+        v = _mm256_permute4x64_epi64(v, 0b00_01_10_11);
+        let lo_mask = _mm256_set1_epi8(0x0F);
+        let lo = _mm256_and_si256(lo_mask, v);
+        let hi = _mm256_srli_epi16(_mm256_andnot_si256(lo_mask, v), 4);
+        #[allow(overflowing_literals)]
+        let slo = _mm256_shuffle_epi8(
+            _mm256_setr_epi8(
+                0x00, 0x40, 0x80, 0xc0, 0x10, 0x50, 0x90, 0xd0, 0x20, 0x60, 0xa0, 0xe0, 0x30, 0x70,
+                0xb0, 0xf0, 0x00, 0x40, 0x80, 0xc0, 0x10, 0x50, 0x90, 0xd0, 0x20, 0x60, 0xa0, 0xe0,
+                0x30, 0x70, 0xb0, 0xf0,
+            ),
+            lo,
+        );
+        let shi = _mm256_shuffle_epi8(
+            _mm256_setr_epi8(
+                0x00, 0x04, 0x08, 0x0c, 0x01, 0x05, 0x09, 0x0d, 0x02, 0x06, 0x0a, 0x0e, 0x03, 0x07,
+                0x0b, 0x0f, 0x00, 0x04, 0x08, 0x0c, 0x01, 0x05, 0x09, 0x0d, 0x02, 0x06, 0x0a, 0x0e,
+                0x03, 0x07, 0x0b, 0x0f,
+            ),
+            hi,
+        );
+        _mm256_storeu_si256(dst.as_mut_ptr() as *mut __m256i, _mm256_or_si256(slo, shi));
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    const ODD_BITS_64: u64 = 0x5555_5555_5555_5555;
+
+    // reversing the bytes and then the bits of each byte reverses the whole
+    // word; swapping adjacent bits afterwards restores each unit's bit order
+    #[target_feature(enable = "neon")]
+    pub unsafe fn reverse_u64_pack_2(data: u64) -> u64 {
+        let v = vrbit_u8(vrev64_u8(vcreate_u8(data)));
+        let data = vget_lane_u64::<0>(vreinterpret_u64_u8(v));
+        (data >> 1 & ODD_BITS_64) | (data & ODD_BITS_64) << 1
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn reverse_u128_pack_2(data: u128) -> u128 {
+        let odd_bits = vdupq_n_u8(0x55);
+        let mut v = vld1q_u8(data.to_le_bytes().as_ptr());
+        v = vrbitq_u8(vextq_u8::<8>(vrev64q_u8(v), vrev64q_u8(v)));
+        v = vorrq_u8(
+            vandq_u8(vshrq_n_u8::<1>(v), odd_bits),
+            vshlq_n_u8::<1>(vandq_u8(v, odd_bits)),
+        );
+        let mut out = [0u8; 16];
+        vst1q_u8(out.as_mut_ptr(), v);
+        u128::from_le_bytes(out)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::testutil::next_random;
+    use crate::testutil::random_seq;
+    use crate::testutil::reverse_complement;
 
     type KMer31 = KMeru64<DNA, 31>;
     #[test]
@@ -325,8 +1362,8 @@ mod test {
     #[test]
     pub fn test_get_unit() {
         let read = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
-        let mut kmer = read.parse::<KMer31>().unwrap();
-        kmer.set_unit(1, DNA::to_unit(b'C').unwrap());
+        let kmer = read.parse::<KMer31>().unwrap();
+        let kmer = kmer.with_unit(1, DNA::to_unit(b'C').unwrap());
         assert_eq!(DNA::to_u8(kmer.get_unit(1)).unwrap(), b'C');
     }
 
@@ -362,4 +1399,469 @@ mod test {
         let kmer_e = "CCCAAAAAAAAAAAAAAAAAAAAAAAAAAAT".parse::<KMer31>().unwrap();
         assert_eq!(kmer.extend(b'T').unwrap(), kmer_e);
     }
+
+    type KMer63 = KMeru128<DNA, 63>;
+    #[test]
+    pub fn test_u128_parse() {
+        let read = "TCGCGTAGCTAGCATATATTCGCGGCTAGTACGATCGATCGGGCTAGCTATTTACGACTAGCA";
+        let kmer = read.parse::<KMer63>().unwrap();
+        assert_eq!(&kmer.to_string(), &read[..KMer63::kmer_len()]);
+    }
+
+    #[test]
+    pub fn test_u128_reverse_complement() {
+        let read = "ACGTTGCAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACCCG";
+        let kmer = read.parse::<KMer63>().unwrap();
+        let rev: String = read.chars().rev().collect();
+        assert_eq!(kmer.reverse().to_string(), rev);
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(read.as_bytes())
+        );
+        assert_eq!(
+            kmer.get_canonical(),
+            kmer.reverse().complement().get_canonical()
+        );
+    }
+
+    #[test]
+    pub fn test_u128_extend() {
+        let read = "CCCCAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAG";
+        let kmer = read.parse::<KMer63>().unwrap();
+        let extended = format!("{}T", &read[1..]);
+        assert_eq!(kmer.extend(b'T').unwrap().to_string(), extended);
+    }
+
+    type KMer77 = KMerArr<DNA, 77, 3>;
+
+    #[test]
+    pub fn test_arr_parse() {
+        let read = "TCGCGTAGCTAGCATATATTCGCGGCTAGTACGATCGATCGGGCTAGCTATTTACGACTAGCAGGCATCGATTTAGCG";
+        let kmer = read.parse::<KMer77>().unwrap();
+        assert_eq!(&kmer.to_string(), &read[..KMer77::kmer_len()]);
+        assert!(read[..76].parse::<KMer77>().is_err());
+
+        let kmer31 = read.parse::<KMerArr<DNA, 31, 1>>().unwrap();
+        assert_eq!(kmer31.data[0], read.parse::<KMer31>().unwrap().data);
+    }
+
+    #[test]
+    pub fn test_arr_reverse_complement() {
+        let read = "ACGTTGCAGGCATTTCAGCTAGCTAGGATCGACTCGATCGGGATTACAGCCCGATCGACTGACCCCGTACGATCAGC";
+        let kmer = read[..77].parse::<KMer77>().unwrap();
+        let rev: String = read[..77].chars().rev().collect();
+        assert_eq!(kmer.reverse().to_string(), rev);
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(&read.as_bytes()[..77])
+        );
+        assert_eq!(
+            kmer.get_canonical(),
+            kmer.reverse().complement().get_canonical()
+        );
+    }
+
+    #[test]
+    pub fn test_arr_extend() {
+        let read = "ACGTTGCAGGCATTTCAGCTAGCTAGGATCGACTCGATCGGGATTACAGCCCGATCGACTGACCCCGTACGATCAGCTTGACAGGATCCA";
+        let mut kmer = read.parse::<KMer77>().unwrap();
+        for (i, c) in read.bytes().enumerate().skip(77) {
+            kmer = kmer.extend(c).unwrap();
+            assert_eq!(kmer.to_string(), read[i + 1 - 77..=i]);
+        }
+        assert!(kmer.extend(b'N').is_none());
+    }
+
+    #[test]
+    pub fn test_arr_order() {
+        let a = format!("{}C", "G".repeat(76)).parse::<KMer77>().unwrap();
+        let b = format!("T{}", "A".repeat(76)).parse::<KMer77>().unwrap();
+        assert!(a < b);
+    }
+
+    #[test]
+    pub fn test_parse_error() {
+        let err = "ACGT".parse::<KMer31>().unwrap_err();
+        assert_eq!(
+            err,
+            KmerParseError::TooShort {
+                len: 4,
+                kmer_len: 31
+            }
+        );
+        assert_eq!(err.to_string(), "got 4 bases, a k-mer needs 31");
+
+        let read = "GATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTACGGANTGA";
+        assert_eq!(
+            read.parse::<KMer63>().unwrap_err(),
+            KmerParseError::InvalidByte {
+                byte: b'N',
+                pos: 59
+            }
+        );
+        assert!(read.parse::<KMer31>().is_ok());
+        let mut bytes = vec![b'A'; 76];
+        bytes.push(b'\t');
+        let err = KMer77::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.to_string(), "invalid base '\\t' at position 76");
+    }
+
+    #[test]
+    pub fn test_from_u128() {
+        let read = "GATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTACGGACTGA";
+        let kmer = read.parse::<KMer63>().unwrap();
+        assert_eq!(KMer63::from_u128(kmer.data), kmer);
+        assert_eq!(KMer31::from_u128(kmer.data).to_string(), &read[32..63]);
+        assert_eq!(
+            KMerArr::<DNA, 63, 2>::from_u128(kmer.data).to_string(),
+            &read[..63]
+        );
+        let kmer77 = KMer77::from_u128(kmer.data).to_string();
+        assert_eq!(kmer77, format!("{}{}", "A".repeat(14), &read[..63]));
+    }
+
+    fn check_with_unit<K: AbstractKMer + std::fmt::Debug>(read: &str, base: u8) {
+        let kmer = K::from_bytes(read.as_bytes()).unwrap();
+        let unit = K::Alphabet::to_unit(base).unwrap();
+        for at in 0..K::kmer_len() {
+            assert_eq!(
+                kmer.unit(at),
+                K::Alphabet::to_unit(read.as_bytes()[at]).unwrap()
+            );
+            let mut expected = read.as_bytes()[..K::kmer_len()].to_vec();
+            expected[at] = base;
+            assert_eq!(kmer.with_unit(at, unit), K::from_bytes(&expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_with_unit() {
+        let read = "GATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTACGGACTGAGATTACAGATTACA";
+        check_with_unit::<KMer31>(read, b'C');
+        check_with_unit::<KMer63>(read, b'G');
+        check_with_unit::<KMer77>(read, b'T');
+        let peptide =
+            "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEVQAPILSRVGDGTQDNLSGAEKAVQVKVKALPDAQFEVVHSLAKWKRQ";
+        check_with_unit::<KMerArr<Protein, 30, 3>>(peptide, b'W');
+    }
+
+    fn check_de_bruijn<K, P>(read: &str, bases: &[u8])
+    where
+        K: AbstractKMer<Alphabet = DNA> + Debug,
+        P: AbstractKMer<Alphabet = DNA> + Debug,
+    {
+        let k = K::kmer_len();
+        let kmer = K::from_bytes(read.as_bytes()).unwrap();
+        assert_eq!(
+            kmer.prefix::<P>(),
+            P::from_bytes(&read.as_bytes()[..k - 1]).unwrap()
+        );
+        assert_eq!(
+            kmer.suffix::<P>(),
+            P::from_bytes(&read.as_bytes()[1..]).unwrap()
+        );
+        assert_eq!(kmer.sub_kmer::<P>(1..k), kmer.suffix());
+        assert_eq!(kmer.extend(bases[0]).unwrap().prefix::<P>(), kmer.suffix());
+        assert_eq!(kmer.prepend(bases[0]).unwrap().suffix::<P>(), kmer.prefix());
+        assert!(kmer.prepend(b'N').is_none());
+
+        let successors: Vec<_> = kmer.successors().collect();
+        let predecessors: Vec<_> = kmer.predecessors().collect();
+        assert_eq!(successors.len(), 4);
+        for (i, &base) in bases.iter().enumerate() {
+            let next = format!("{}{}", &read[1..], base as char);
+            assert_eq!(successors[i], K::from_bytes(next.as_bytes()).unwrap());
+            let prev = format!("{}{}", base as char, &read[..k - 1]);
+            assert_eq!(predecessors[i], K::from_bytes(prev.as_bytes()).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_de_bruijn() {
+        let read = "GATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTACGGACTGAGATTACAGATTACA";
+        check_de_bruijn::<KMeru64<DNA, 7>, KMeru64<DNA, 6>>(&read[..7], b"ACGT");
+        check_de_bruijn::<KMer63, KMeru128<DNA, 62>>(&read[..63], b"ACGT");
+        check_de_bruijn::<KMer77, KMerArr<DNA, 76, 3>>(read, b"ACGT");
+        check_de_bruijn::<KMeru64<DNA, 31>, KMeru64<DNA, 30>>(&read[..31], b"ACGT");
+
+        let kmer = read.parse::<KMer77>().unwrap();
+        let middle: KMer63 = kmer.sub_kmer(7..70);
+        assert_eq!(middle.to_string(), &read[7..70]);
+        let middle: KMerArr<DNA, 40, 2> = kmer.sub_kmer(30..70);
+        assert_eq!(middle.to_string(), &read[30..70]);
+        let middle: KMer31 = read.parse::<KMer63>().unwrap().sub_kmer(20..51);
+        assert_eq!(middle.to_string(), &read[20..51]);
+
+        let peptide = "MKTAYIAKQRQISFVKSHFSRQ"
+            .parse::<KMeru128<Protein, 22>>()
+            .unwrap();
+        assert_eq!(peptide.successors().count(), 20);
+        assert_eq!(peptide.predecessors().count(), 20);
+        let sub: Peptide12 = peptide.sub_kmer(5..17);
+        assert_eq!(sub.to_string(), "IAKQRQISFVKS");
+        let peptide = "MKTAYIAKQRQISFVKSHFSRQLEERLGLI"
+            .parse::<KMerArr<Protein, 30, 3>>()
+            .unwrap();
+        let suffix: KMerArr<Protein, 29, 3> = peptide.suffix();
+        assert_eq!(suffix.to_string(), "KTAYIAKQRQISFVKSHFSRQLEERLGLI");
+        assert_eq!(
+            peptide.prepend(b'W').unwrap().to_string(),
+            "WMKTAYIAKQRQISFVKSHFSRQLEERLGL"
+        );
+    }
+
+    type Peptide12 = KMeru64<Protein, 12>;
+    #[test]
+    pub fn test_protein() {
+        let read = "MKTAYIAKQRQISFVKSHFSRQ";
+        let kmer = read.parse::<Peptide12>().unwrap();
+        assert_eq!(&kmer.to_string(), &read[..12]);
+        let rev: String = read[..12].chars().rev().collect();
+        assert_eq!(kmer.reverse().to_string(), rev);
+        assert_eq!(kmer.complement(), kmer);
+        assert_eq!(kmer.get_canonical(), kmer);
+        assert_eq!(kmer.extend(b'S').unwrap().to_string(), &read[1..13]);
+        assert!(kmer.extend(b'B').is_none());
+
+        let kmer = read.parse::<KMeru128<Protein, 22>>().unwrap();
+        assert_eq!(kmer.to_string(), read);
+        assert_eq!(
+            kmer.reverse().to_string(),
+            read.chars().rev().collect::<String>()
+        );
+    }
+
+    #[test]
+    pub fn test_protein_arr() {
+        // 5-bit units straddle the word boundaries
+        let read =
+            "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEVQAPILSRVGDGTQDNLSGAEKAVQVKVKALPDAQFEVVHSLAKWKRQ";
+        let mut kmer = read.parse::<KMerArr<Protein, 30, 3>>().unwrap();
+        assert_eq!(kmer.to_string(), &read[..30]);
+        let rev: String = read[..30].chars().rev().collect();
+        assert_eq!(kmer.reverse().to_string(), rev);
+        for (i, c) in read.bytes().enumerate().skip(30) {
+            kmer = kmer.extend(c).unwrap();
+            assert_eq!(kmer.to_string(), read[i + 1 - 30..=i]);
+        }
+
+        let a = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAY"
+            .parse::<KMerArr<Protein, 30, 3>>()
+            .unwrap();
+        let b = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+            .parse::<KMerArr<Protein, 30, 3>>()
+            .unwrap();
+        assert!(a < b);
+    }
+
+    // same bases as DNA, but complements are not bit inverses
+    struct SwappedDNA {}
+
+    impl Alphabet for SwappedDNA {
+        const UNIT_LEN: usize = 2;
+        const HAS_COMPLEMENT: bool = true;
+        fn to_unit(base: u8) -> Option<u8> {
+            Some(match base {
+                b'A' => 0,
+                b'C' => 1,
+                b'T' => 2,
+                b'G' => 3,
+                _ => return None,
+            })
+        }
+        fn to_u8(unit: u8) -> Option<u8> {
+            b"ACTG".get(unit as usize).copied()
+        }
+        fn complement(unit: u8) -> u8 {
+            unit ^ 0b10
+        }
+    }
+
+    #[test]
+    pub fn test_alphabet_complement() {
+        let read = "ACGTTGCAGGCATTTCAGCTAGCTAGGATCGACTCGATCGGGATTACAGCCCGATCGACTGACCCCGTACGATCAGC";
+        let kmer = read.parse::<KMeru64<SwappedDNA, 31>>().unwrap();
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(&read.as_bytes()[..31])
+        );
+        let kmer = read.parse::<KMeru128<SwappedDNA, 63>>().unwrap();
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(&read.as_bytes()[..63])
+        );
+        let kmer = read.parse::<KMerArr<SwappedDNA, 77, 3>>().unwrap();
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(&read.as_bytes()[..77])
+        );
+
+        for i in 0..4 {
+            assert_eq!(DNA::complement(i), !i & 0b11);
+        }
+    }
+
+    #[test]
+    pub fn test_rna() {
+        let dna = "ACGTTGCAGGCATTTCAGCTAGCTAGGATCG";
+        let rna = dna.replace('T', "U");
+        let kmer = rna.parse::<KMeru64<RNA, 31>>().unwrap();
+        assert_eq!(kmer.data, dna.parse::<KMer31>().unwrap().data);
+        assert_eq!(kmer.to_string(), rna);
+        assert_eq!(kmer.extend(b'T').unwrap(), kmer.extend(b'U').unwrap());
+
+        let kmer = rna.parse::<KMeru64<RNA<b'T'>, 31>>().unwrap();
+        assert_eq!(kmer.to_string(), dna);
+        assert_eq!(
+            kmer.reverse().complement().to_string().into_bytes(),
+            reverse_complement(dna.as_bytes())
+        );
+    }
+
+    #[test]
+    pub fn test_lowercase() {
+        let kmer = "acgtTGCAggcatttcagctagctaggatcg".parse::<KMer31>().unwrap();
+        assert_eq!(kmer.to_string(), "ACGTTGCAGGCATTTCAGCTAGCTAGGATCG");
+        let kmer = "acguu".parse::<KMeru64<RNA, 5>>().unwrap();
+        assert_eq!(kmer.to_string(), "ACGUU");
+    }
+
+    #[test]
+    pub fn test_reverse_backends() {
+        let mut state = 0x0123456789ABCDEF;
+        for _ in 0..1000 {
+            let data = next_random(&mut state);
+            let reversed = scalar::reverse_u64_pack_2(data);
+            assert_eq!(scalar::reverse_u64_pack_2(reversed), data);
+            assert_eq!(reverse_u64_pack_2(data), reversed);
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if is_x86_feature_detected!("ssse3") {
+                    assert_eq!(unsafe { ssse3::reverse_u64_pack_2(data) }, reversed);
+                }
+            }
+
+            let wide = (data as u128) << 64 | next_random(&mut state) as u128;
+            let reversed = scalar::reverse_u128_pack_2(wide);
+            assert_eq!(scalar::reverse_u128_pack_2(reversed), wide);
+            assert_eq!(reverse_u128_pack_2(wide), reversed);
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if is_x86_feature_detected!("ssse3") {
+                    assert_eq!(unsafe { ssse3::reverse_u128_pack_2(wide) }, reversed);
+                }
+            }
+        }
+        assert_eq!(scalar::reverse_u64_pack_2(0b01), 0b01 << 62);
+        assert_eq!(scalar::reverse_u128_pack_2(0b01), 0b01 << 126);
+    }
+
+    #[test]
+    pub fn test_reverse_words() {
+        let mut state = 0xFEDCBA9876543210;
+        for len in 1..10 {
+            let src: Vec<u64> = (0..len).map(|_| next_random(&mut state)).collect();
+            let mut dst = vec![0; len];
+            reverse_words_pack_2(&src, &mut dst);
+            for i in 0..len {
+                assert_eq!(dst[i], scalar::reverse_u64_pack_2(src[len - 1 - i]));
+            }
+        }
+    }
+
+    fn check_canonical_kmers<K>(read: &str)
+    where
+        K: AbstractKMer + Debug,
+    {
+        let mut expected = vec![];
+        for start in 0..(read.len() + 1).saturating_sub(K::kmer_len()) {
+            if let Ok(kmer) = K::from_bytes(&read.as_bytes()[start..]) {
+                let canonical = kmer.get_canonical();
+                let strand = if canonical == kmer {
+                    Strand::Forward
+                } else {
+                    Strand::Reverse
+                };
+                expected.push((start, canonical, strand));
+            }
+        }
+        let rolled: Vec<_> = CanonicalKmers::<K>::new(read.as_bytes()).collect();
+        assert_eq!(rolled, expected);
+    }
+
+    #[test]
+    pub fn test_canonical_kmers() {
+        let read = String::from_utf8(random_seq(300)).unwrap();
+        check_canonical_kmers::<KMeru64<DNA, 5>>(&read);
+        check_canonical_kmers::<KMer31>(&read);
+        check_canonical_kmers::<KMeru64<DNA, 32>>(&read);
+        check_canonical_kmers::<KMer63>(&read);
+        check_canonical_kmers::<KMeru128<DNA, 64>>(&read);
+        check_canonical_kmers::<KMer77>(&read);
+        check_canonical_kmers::<KMerArr<SwappedDNA, 45, 2>>(&read);
+
+        let peptides = "MKTAYIAKQRQISFVKSHFSRQLEERLGLIEVQAPILSRVGDGTQDNLSGAEKAVQVKVKALPDAQ";
+        check_canonical_kmers::<Peptide12>(peptides);
+        check_canonical_kmers::<KMerArr<Protein, 30, 3>>(peptides);
+    }
+
+    #[test]
+    pub fn test_invertible_hash() {
+        let mut state = 0x0123456789ABCDEF;
+        for _ in 0..1000 {
+            let key = next_random(&mut state);
+            assert_eq!(unmix64(mix64(key)), key);
+        }
+        assert_ne!(mix64(0), 0);
+
+        let kmer = "GATTACAGATTACAGGCTTCAGCTACGATCA".parse::<KMer31>().unwrap();
+        for &seed in &[0, 42] {
+            let hasher = InvertibleHash::new(seed);
+            let hash = hasher.hash(&kmer);
+            assert_eq!(hasher.invert::<DNA, 31>(hash), kmer);
+        }
+        assert_ne!(
+            InvertibleHash::new(0).hash(&kmer),
+            InvertibleHash::new(42).hash(&kmer)
+        );
+
+        let read = "GATTACAGATTACAGGCTTCAGCTACGATCAGCATTAGCTTTTTTTTTTTTTTTACGGACTGAGATTACAGATTACA";
+        let wide = read.parse::<KMer77>().unwrap();
+        let mut words = vec![];
+        wide.to_words(&mut words);
+        assert_eq!(words.len(), 3);
+        assert_eq!(KMer77::from_words(&words), wide);
+        let hasher = InvertibleHash::new(42);
+        assert_eq!(hasher.hash_words(&words), hasher.hash(&wide));
+    }
+
+    #[test]
+    pub fn test_nthash() {
+        fn check<K>(read: &[u8])
+        where
+            K: AbstractKMer,
+            K::Alphabet: Nucleotides,
+        {
+            let rolled: Vec<_> = NtHashes::<K>::new(read).collect();
+            let expected: Vec<_> = CanonicalKmers::<K>::new(read)
+                .map(|(pos, kmer, _)| (pos, NtHash.hash(&kmer)))
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(rolled, expected);
+        }
+        let read = String::from_utf8(random_seq(2000)).unwrap();
+        check::<KMer31>(read.as_bytes());
+        check::<KMeru128<DNA, 45>>(read.as_bytes());
+        check::<KMerArr<DNA, 77, 3>>(read.as_bytes());
+        check::<KMeru64<RNA, 21>>(read.as_bytes());
+
+        let (pos, _, _) = CanonicalKmers::<KMer31>::new(read.as_bytes())
+            .next()
+            .unwrap();
+        let kmer = &read[pos..pos + 31];
+        let forward = NtHash.hash(&kmer.parse::<KMer31>().unwrap());
+        let reverse =
+            NtHash.hash(&KMer31::from_bytes(&reverse_complement(kmer.as_bytes())).unwrap());
+        assert_eq!(forward, reverse);
+    }
 }
@@ -0,0 +1,28 @@
+//! K-mer counting shared by the `kmcrayfish` and `kmcrayfish2` front-ends,
+//! which distribute the same work across crayfish places.
+//!
+//! `count_kmers` counts input files in the calling process:
+//!
+//! ```no_run
+//! use kmcrayfish::{count_kmers, Config};
+//!
+//! let table = count_kmers(&["reads.fq"], Config::new(31)).unwrap();
+//! println!("{} distinct 31-mers", table.len());
+//! ```
+
+pub mod count;
+pub mod input;
+pub mod kmer;
+pub mod minimizer;
+pub mod neighbors;
+pub mod reader;
+pub mod sampling;
+pub mod spaced;
+pub mod split;
+pub mod superkmer;
+#[cfg(test)]
+mod testutil;
+
+pub use count::count_kmers;
+pub use count::Config;
+pub use count::CountTable;
@@ -1,4 +1,4 @@
-mod kmer;
+mod cli;
 
 use crayfish::collective;
 use crayfish::finish;
@@ -8,24 +8,21 @@ use crayfish::logging::*;
 use crayfish::place::Place;
 use crayfish::shared::PlaceLocal;
 use crayfish::shared::PlaceLocalWeak;
-use std::collections::hash_map::DefaultHasher;
-use std::fs::File;
-use std::hash::Hash;
-use std::hash::Hasher;
-use std::io;
-use std::io::BufRead;
-use std::io::BufReader;
 use std::sync::Mutex;
 
-use kmer::AbstractKMer;
-use kmer::KMeru64;
-use kmer::DNA;
-
-const KMER_LEN: usize = 31;
+use kmcrayfish::count;
+use kmcrayfish::count::Bases;
+use kmcrayfish::count::ReadSplitter;
+use kmcrayfish::input;
+use kmcrayfish::superkmer;
+use kmcrayfish::CountTable;
 
 type Reads = Vec<Vec<u8>>;
+// bases sent to a place at a time, about 40960 short reads; counted in
+// bases since a FASTA read is a chunk of up to reader::FASTA_CHUNK_LEN
+const BATCH_BASES: usize = 40960 * 150;
+// raw words of the k-mers, see AbstractKMer::to_words
 type CountBin = Vec<u64>;
-type KMer = KMeru64<DNA, KMER_LEN>;
 
 #[crayfish::activity]
 async fn update_kmer(kmers: Vec<u64>, final_ptr: PlaceLocalWeak<Mutex<CountBin>>) {
@@ -34,106 +31,58 @@ async fn update_kmer(kmers: Vec<u64>, final_ptr: PlaceLocalWeak<Mutex<CountBin>>
     h.extend_from_slice(&kmers[..]);
 }
 
-fn get_partition(kmer: &KMer) -> usize {
-    let mut hasher = DefaultHasher::new();
-    kmer.hash(&mut hasher);
-    (hasher.finish() % place::world_size() as u64) as usize
+#[crayfish::activity]
+async fn update_super_kmers(
+    kmer_len: usize,
+    packed: Vec<u64>,
+    final_ptr: PlaceLocalWeak<Mutex<CountBin>>,
+) {
+    let mut kmers = vec![];
+    count::unpack_super_kmers(kmer_len, &packed, &mut kmers);
+    let ptr = final_ptr.upgrade().unwrap();
+    let mut h = ptr.lock().unwrap();
+    h.extend_from_slice(&kmers[..]);
 }
 
 #[crayfish::activity]
-async fn kmer_counting(reads: Reads, final_ptr: PlaceLocalWeak<Mutex<CountBin>>) {
+async fn kmer_counting(
+    reads: Reads,
+    args_ptr: PlaceLocalWeak<cli::Args>,
+    final_ptr: PlaceLocalWeak<Mutex<CountBin>>,
+) {
     info!("Got {} reads. Spliting into Kmers", reads.len());
+    let args = args_ptr.upgrade().unwrap();
 
-    let mut kmers = vec![vec![]; place::world_size()];
+    let world_size = place::world_size();
+    let mut kmers = vec![vec![]; world_size];
+    let mut splitter = ReadSplitter::new(args.config.clone());
     for read in reads {
-        // drop too short read
-        if read.len() < KMer::kmer_len() {
-            continue;
-        }
-
-        let mut next_pos = 0;
-        let mut start = true;
-        let end = read.len();
-        let mut current_kmer = KMer::new(0); // fake start, won't be extended
-        while next_pos < end {
-            if start {
-                match KMer::from_bytes(&read[next_pos..]) {
-                    Some(k) => {
-                        let k = k.get_canonical();
-                        // TODO should depends on trait. struct field k.data used here
-                        kmers[get_partition(&k)].push(k.data);
-                        current_kmer = k;
-                        next_pos += KMer::kmer_len();
-                        start = false;
-                    }
-                    None => {
-                        next_pos += 1;
-                    }
-                }
-            } else {
-                match current_kmer.extend(read[next_pos]) {
-                    Some(k) => {
-                        let k = k.get_canonical();
-                        kmers[get_partition(&k)].push(k.data);
-                        current_kmer = k;
-                    }
-                    None => {
-                        start = true;
-                    }
-                }
-                next_pos += 1;
-            }
+        if args.super_kmers {
+            // the lists hold packed super-k-mers instead, see superkmer::pack
+            splitter.super_kmers(&read, |minimizer, bases| {
+                superkmer::pack::<Bases>(&mut kmers[count::partition(minimizer, world_size)], bases)
+            })
+        } else {
+            splitter.kmers(&read, |k| {
+                kmers[count::partition_words(k, world_size)].extend_from_slice(k)
+            })
         }
     }
+    let stats = splitter.stats();
+    info!(
+        "Dropped {} soft-masked and {} invalid bases, {} k-mers lost to {} ambiguous bases",
+        stats.masked_bases, stats.invalid_bases, stats.ambiguous_kmers, stats.ambiguous_bases
+    );
 
     info!("Sending kmers to destination");
     for (dst, kmer_list) in kmers.into_iter().enumerate() {
-        crayfish::ff!(dst as Place, update_kmer(kmer_list, final_ptr.clone()));
-    }
-}
-
-// TODO: stupid fasta/fastq reader
-struct SeqReader<I>
-where
-    I: Iterator<Item = io::Result<String>>,
-{
-    lines: I,
-}
-
-impl<T> SeqReader<T>
-where
-    T: Iterator<Item = io::Result<String>>,
-{
-    pub fn new(lines: T) -> Self {
-        SeqReader { lines }
-    }
-}
-
-impl<T> Iterator for SeqReader<T>
-where
-    T: Iterator<Item = io::Result<String>>,
-{
-    type Item = Vec<u8>;
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut skip_q = false;
-        loop {
-            let line = self.lines.next()?.unwrap().into_bytes();
-            if !line.is_empty() {
-                match line[0] {
-                    b'@' => {
-                        skip_q = false;
-                    }
-                    b'>' => (),
-                    b'+' => {
-                        skip_q = true;
-                    }
-                    _ => {
-                        if !skip_q {
-                            return Some(line);
-                        }
-                    }
-                }
-            }
+        if args.super_kmers {
+            crayfish::ff!(
+                dst as Place,
+                update_super_kmers(args.config.kmer_len, kmer_list, final_ptr.clone())
+            );
+        } else {
+            crayfish::ff!(dst as Place, update_kmer(kmer_list, final_ptr.clone()));
         }
     }
 }
@@ -141,64 +90,66 @@ where
 // desugered finish
 #[crayfish::main]
 async fn inner_main() {
+    // every place sees the same command line
+    let args = match cli::parse_args() {
+        Ok(args) => PlaceLocal::new(args),
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::usage();
+            return;
+        }
+    };
     let count_bin = PlaceLocal::new(Mutex::new(CountBin::default()));
     collective::barrier().await;
     if place::here() == 0 {
         // ctx contains a new finish id now
-        let chunk_size = 40960;
-        let args = std::env::args().collect::<Vec<_>>();
-        let filename = &args[1];
-        let file = File::open(filename).unwrap();
-        let lines = BufReader::new(file).lines();
-        let lines = SeqReader::new(lines.into_iter());
+        let filename = &args.filename;
+        let file = input::open(filename).unwrap_or_else(|e| panic!("{}: {}", filename, e));
+        let lines = args
+            .config
+            .seq_reader(file)
+            .unwrap_or_else(|e| panic!("{}: {}", filename, e))
+            .map(|read| read.unwrap_or_else(|e| panic!("{}: {}", filename, e)));
 
         let world_size = world_size();
         let mut next_place: Place = 0;
         let mut buffer: Reads = vec![];
+        let mut buffered = 0;
+        let mut first = 0;
 
         finish! {
         for (l_num, line) in lines.enumerate() {
-                if buffer.len() == chunk_size {
-                    info!(
-                        "Sending {}~{} reads to {}",
-                        l_num,
-                        l_num + chunk_size - 1,
-                        next_place + 1
-                    );
-                    let mut new_read = vec![];
-                    std::mem::swap(&mut new_read, &mut buffer);
-                    crayfish::ff!(next_place + 1, kmer_counting(new_read, count_bin.downgrade()));
+                buffered += line.len();
+                buffer.push(line);
+                if buffered >= BATCH_BASES {
+                    info!("Sending {}~{} reads to {}", first, l_num, next_place + 1);
+                    let new_read = std::mem::take(&mut buffer);
+                    crayfish::ff!(next_place + 1, kmer_counting(new_read, args.downgrade(), count_bin.downgrade()));
                     next_place = (next_place + 1) % (world_size as Place - 1); // avoid root
+                    buffered = 0;
+                    first = l_num + 1;
                 }
-                buffer.push(line);
+        }
+        if !buffer.is_empty() {
+            info!("Sending {}~{} reads to {}", first, first + buffer.len() - 1, next_place + 1);
+            crayfish::ff!(next_place + 1, kmer_counting(buffer, args.downgrade(), count_bin.downgrade()));
         }
         }
     }
     collective::barrier().await;
 
     let mut sorted_bin = count_bin.lock().unwrap();
-    use crate::kmer::radix::RadixSort;
-    sorted_bin.voracious_sort();
-
-    let mut hist = vec![0usize; 1024];
-    let hist_len = hist.len();
-
-    if sorted_bin.is_empty() {
-        return
-    }
-    let mut current = sorted_bin[0];
-    let mut count = 0;
-
-    for kmer in sorted_bin.iter() {
-        if current != *kmer {
-            if count <= hist_len {
-                hist[count - 1] += 1;
-            }
-            current = *kmer;
-            count = 1;
-        }else{
-            count += 1;
+    let counts = CountTable::from_words(args.config.kmer_len, &mut sorted_bin);
+    // every place writes its own k-mers, empty ones included
+    if let Some(output) = &args.output {
+        let path = count::part_path(output, place::here() as usize);
+        if let Err(e) = counts.dump(&path) {
+            eprintln!("{}: {}", path.display(), e);
+            return;
         }
     }
-    info!("{:?}", hist);
+    if counts.is_empty() {
+        return;
+    }
+    info!("{:?}", counts.histogram(1024));
 }
@@ -1,4 +1,4 @@
-mod kmer;
+mod cli;
 
 use crayfish::collective;
 use crayfish::finish;
@@ -8,17 +8,18 @@ use crayfish::place::world_size;
 use crayfish::place::Place;
 use crayfish::shared::PlaceLocal;
 use crayfish::shared::PlaceLocalWeak;
+use memmap::Mmap;
 use std::fs::File;
 use std::sync::Mutex;
 
-use kmer::AbstractKMer;
-use kmer::KMeru64;
-use kmer::DNA;
-
-const KMER_LEN: usize = 31;
+use kmcrayfish::count;
+use kmcrayfish::count::Bases;
+use kmcrayfish::count::ReadSplitter;
+use kmcrayfish::superkmer;
+use kmcrayfish::CountTable;
 
+// raw words of the k-mers, see AbstractKMer::to_words
 type CountBin = Vec<u64>;
-type KMer = KMeru64<DNA, KMER_LEN>;
 
 #[crayfish::activity]
 async fn update_kmer(kmers: Vec<u64>, final_ptr: PlaceLocalWeak<Mutex<CountBin>>) {
@@ -27,93 +28,17 @@ async fn update_kmer(kmers: Vec<u64>, final_ptr: PlaceLocalWeak<Mutex<CountBin>>
     h.extend_from_slice(&kmers[..]);
 }
 
-fn get_partition(kmer: &KMer) -> usize {
-    let mut key = kmer.data;
-    key = !key + (key << 21);
-    key = key ^ key >> 24;
-    key = (key + (key << 3)) + (key << 8);
-    key = key ^ key >> 14;
-    key = (key + (key << 2)) + (key << 4);
-    key = key ^ key >> 28;
-    key = key + (key << 31);
-    (key % place::world_size() as u64) as usize
-    // let key = kmer.data;
-    // let mut hs = rustc_hash::FxHasher::default();
-    // key.hash(&mut hs);
-    // (hs.finish() % place::world_size() as u64) as usize
-}
-
-// TODO: stupid fasta/fastq reader
-struct SeqReader<I> {
-    lines: I,
-}
-
-impl<T> SeqReader<T> {
-    pub fn new(lines: T) -> Self {
-        SeqReader { lines }
-    }
-}
-
-impl<'a, T> Iterator for SeqReader<T>
-where
-    T: Iterator<Item = String> + 'a,
-{
-    type Item = Vec<u8>;
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut skip_q = false;
-        loop {
-            let line = self.lines.next()?;
-            if !line.is_empty() {
-                let line = line.into_bytes();
-                match line[0] {
-                    b'@' => {
-                        skip_q = false;
-                    }
-                    b'>' => (),
-                    b'+' => {
-                        skip_q = true;
-                    }
-                    _ => {
-                        if !skip_q {
-                            return Some(line);
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
-struct Lines<'a> {
-    data: &'a [u8],
-}
-impl<'a> Lines<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Lines { data }
-    }
-}
-
-impl<'a> Iterator for Lines<'a> {
-    type Item = &'a [u8];
-    fn next(&mut self) -> Option<Self::Item> {
-        let pos = memchr::memchr(b'\n', self.data)?;
-        let ret = &self.data[..pos];
-        if pos == self.data.len() - 1 {
-            // is last
-            self.data = &self.data[pos..pos]; // empty
-        } else {
-            self.data = &self.data[pos + 1..];
-        }
-        Some(ret)
-    }
-}
-
-fn usage() {
-    print!(
-        "Usage:
-    kmcrayfish <fasta_file>
-    "
-    );
+#[crayfish::activity]
+async fn update_super_kmers(
+    kmer_len: usize,
+    packed: Vec<u64>,
+    final_ptr: PlaceLocalWeak<Mutex<CountBin>>,
+) {
+    let mut kmers = vec![];
+    count::unpack_super_kmers(kmer_len, &packed, &mut kmers);
+    let ptr = final_ptr.upgrade().unwrap();
+    let mut h = ptr.lock().unwrap();
+    h.extend_from_slice(&kmers[..]);
 }
 
 // desugered finish
@@ -123,109 +48,96 @@ async fn inner_main() {
     collective::barrier().await;
     // ctx contains a new finish id now
     let mut kmers = vec![vec![]; place::world_size()];
-    let args = std::env::args().collect::<Vec<_>>();
-    if args.len() != 2 {
-        usage();
-        return;
-    }
-    let filename = &args[1];
-    let file = File::open(filename).unwrap();
-    use std::io::BufRead;
-    use std::io::BufReader;
-    let bf = BufReader::new(file);
-    let lines = SeqReader::new(bf.lines().map(|lr| lr.unwrap()));
-    // let lines = Lines
-
+    let args = match cli::parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::usage();
+            return;
+        }
+    };
+    let kmer_len = args.config.kmer_len;
+    let super_kmers = args.super_kmers;
+    let filename = &args.filename;
+    let mut splitter = ReadSplitter::new(args.config.clone());
+    let file = File::open(filename).unwrap_or_else(|e| panic!("{}: {}", filename, e));
     let world_size = world_size();
     let here = place::here();
+    // every place maps the whole file but parses only its share of records;
+    // an empty file cannot be mapped and has no records anyway
+    let map;
+    let data: &[u8] = match file.metadata() {
+        Ok(metadata) if metadata.len() == 0 => &[],
+        _ => {
+            map = unsafe { Mmap::map(&file) }.unwrap_or_else(|e| panic!("{}: {}", filename, e));
+            &map
+        }
+    };
+    let lines = count::share_reads(&args.config, data, here as usize, world_size)
+        .unwrap_or_else(|e| panic!("{}: {}", filename, e))
+        .map(|read| read.unwrap_or_else(|e| panic!("{}: {}", filename, e)));
 
     let chunk_size = 40960usize;
 
     finish! {
     for (l_num, read) in lines.enumerate() {
-        if here as usize != l_num % world_size as usize{
-            continue
-        }
-        if read.len() < KMer::kmer_len() {
-            continue;
-        }
-
-        let mut next_pos = 0;
-        let mut start = true;
-        let end = read.len();
-        let mut current_kmer = KMer::new(0); // fake start, won't be extended
-        while next_pos < end {
-            if start {
-                match KMer::from_bytes(&read[next_pos..]) {
-                    Some(k) => {
-                        let k = k.get_canonical();
-                        // TODO should depends on trait. struct field k.data used here
-                        kmers[get_partition(&k)].push(k.data);
-                        current_kmer = k;
-                        next_pos += KMer::kmer_len();
-                        start = false;
-                    }
-                    None => {
-                        next_pos += 1;
-                    }
-                }
-            } else {
-                match current_kmer.extend(read[next_pos]) {
-                    Some(k) => {
-                        let k = k.get_canonical();
-                        kmers[get_partition(&k)].push(k.data);
-                        current_kmer = k;
-                    }
-                    None => {
-                        start = true;
-                    }
-                }
-                next_pos += 1;
-            }
+        if super_kmers {
+            // the lists hold packed super-k-mers instead, see superkmer::pack
+            splitter.super_kmers(&read, |minimizer, bases| {
+                superkmer::pack::<Bases>(&mut kmers[count::partition(minimizer, world_size)], bases)
+            })
+        } else {
+            splitter.kmers(&read, |k| {
+                kmers[count::partition_words(k, world_size)].extend_from_slice(k)
+            })
         }
 
         // interleave communication and computing
-        if l_num / world_size  % chunk_size == 0 {
+        if l_num % chunk_size == 0 {
             let mut new_kmers = vec![vec![]; place::world_size()];
             std::mem::swap(&mut new_kmers, &mut kmers);
             for (dst, kmer_list) in new_kmers.into_iter().enumerate() {
-                crayfish::ff!(dst as Place, update_kmer(kmer_list, count_bin.downgrade()));
+                if super_kmers {
+                    crayfish::ff!(dst as Place, update_super_kmers(kmer_len, kmer_list, count_bin.downgrade()));
+                } else {
+                    crayfish::ff!(dst as Place, update_kmer(kmer_list, count_bin.downgrade()));
+                }
             }
         }
     }
 
     for (dst, kmer_list) in kmers.into_iter().enumerate() {
-        crayfish::ff!(dst as Place, update_kmer(kmer_list, count_bin.downgrade()));
+        if super_kmers {
+            crayfish::ff!(dst as Place, update_super_kmers(kmer_len, kmer_list, count_bin.downgrade()));
+        } else {
+            crayfish::ff!(dst as Place, update_kmer(kmer_list, count_bin.downgrade()));
+        }
     }
-    info!("k-mer gen done");
+    let stats = splitter.stats();
+    info!(
+        "k-mer gen done, dropped {} soft-masked and {} invalid bases, {} k-mers lost to {} ambiguous bases",
+        stats.masked_bases,
+        stats.invalid_bases,
+        stats.ambiguous_kmers,
+        stats.ambiguous_bases
+    );
 
     }
     collective::barrier().await;
     info!("start counting");
 
     let mut sorted_bin = count_bin.lock().unwrap();
-    use crate::kmer::radix::RadixSort;
-    sorted_bin.voracious_sort();
-
-    let mut hist = vec![0usize; 1024];
-    let hist_len = hist.len();
-
-    if sorted_bin.is_empty() {
-        return;
-    }
-    let mut current = sorted_bin[0];
-    let mut count = 0;
-
-    for kmer in sorted_bin.iter() {
-        if current != *kmer {
-            if count <= hist_len {
-                hist[count - 1] += 1;
-            }
-            current = *kmer;
-            count = 1;
-        } else {
-            count += 1;
+    let counts = CountTable::from_words(kmer_len, &mut sorted_bin);
+    // every place writes its own k-mers, empty ones included
+    if let Some(output) = &args.output {
+        let path = count::part_path(output, place::here() as usize);
+        if let Err(e) = counts.dump(&path) {
+            eprintln!("{}: {}", path.display(), e);
+            return;
         }
     }
-    info!("{:?}", hist);
+    if counts.is_empty() {
+        return;
+    }
+    info!("{:?}", counts.histogram(1024));
 }
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use crate::kmer::AbstractKMer;
+use crate::kmer::CanonicalKmers;
+use crate::kmer::Strand;
+
+/// Order in which m-mers compete for being the minimizer of a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MinimizerOrder {
+    /// Smallest forward strand m-mer.
+    Lexicographic,
+    /// Smallest canonical m-mer, the same on both strands.
+    Canonical,
+    /// Smallest seeded hash of the canonical m-mer. Avoids the skew of
+    /// lexicographic minimizers towards poly-A runs.
+    Hash(u64),
+}
+
+/// Seeded hash of a k-mer, for orders that should look random.
+pub fn hash_kmer<K: Hash>(kmer: &K, seed: u64) -> u64 {
+    let mut hasher = rustc_hash::FxHasher::default();
+    seed.hash(&mut hasher);
+    kmer.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Minimizers of every window of `w` consecutive m-mers of a sequence, where
+/// `m` is the length of `M`. Yields `(window start, minimizer position,
+/// minimizer)` with ties going to the leftmost m-mer; the minimizer is
+/// canonical unless the order is `Lexicographic`. Windows never span a base
+/// the alphabet rejects. Each base costs amortized O(1).
+pub struct Minimizers<'a, M> {
+    mmers: CanonicalKmers<'a, M>,
+    w: usize,
+    order: MinimizerOrder,
+    // candidates with increasing keys, the front is the current minimizer
+    candidates: VecDeque<(usize, (u64, M))>,
+    run: usize, // consecutive m-mers seen
+    last_pos: Option<usize>,
+}
+
+impl<'a, M> Minimizers<'a, M>
+where
+    M: AbstractKMer,
+{
+    pub fn new(seq: &'a [u8], w: usize, order: MinimizerOrder) -> Self {
+        assert!(w > 0, "window must hold at least one m-mer");
+        Minimizers {
+            mmers: CanonicalKmers::new(seq),
+            w,
+            order,
+            candidates: VecDeque::with_capacity(w),
+            run: 0,
+            last_pos: None,
+        }
+    }
+
+    fn key(&self, canonical: M, strand: Strand) -> (u64, M) {
+        match self.order {
+            MinimizerOrder::Lexicographic => match strand {
+                Strand::Forward => (0, canonical),
+                Strand::Reverse => (0, canonical.reverse().complement()),
+            },
+            MinimizerOrder::Canonical => (0, canonical),
+            MinimizerOrder::Hash(seed) => (hash_kmer(&canonical, seed), canonical),
+        }
+    }
+}
+
+impl<'a, M> Iterator for Minimizers<'a, M>
+where
+    M: AbstractKMer,
+{
+    type Item = (usize, usize, M);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (pos, canonical, strand) = self.mmers.next()?;
+            if pos == 0 || self.last_pos != Some(pos - 1) {
+                // an invalid base was skipped, start over
+                self.candidates.clear();
+                self.run = 0;
+            }
+            self.last_pos = Some(pos);
+            self.run += 1;
+
+            let key = self.key(canonical, strand);
+            while matches!(self.candidates.back(), Some((_, back)) if *back > key) {
+                self.candidates.pop_back();
+            }
+            self.candidates.push_back((pos, key));
+            while matches!(self.candidates.front(), Some(&(front, _)) if front + self.w <= pos) {
+                self.candidates.pop_front();
+            }
+
+            if self.run >= self.w {
+                let (min_pos, (_, min)) = self.candidates[0];
+                return Some((pos + 1 - self.w, min_pos, min));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::KMeru64;
+    use crate::kmer::DNA;
+    use crate::testutil::random_seq;
+    use crate::testutil::reverse_complement;
+
+    type MMer5 = KMeru64<DNA, 5>;
+
+    fn brute_force(seq: &[u8], w: usize, order: MinimizerOrder) -> Vec<(usize, usize, MMer5)> {
+        let minimizers = Minimizers::<MMer5>::new(seq, w, order);
+        let m = MMer5::kmer_len();
+        let mut ret = vec![];
+        for start in 0..(seq.len() + 1).saturating_sub(m + w - 1) {
+            let mut best: Option<(usize, (u64, MMer5))> = None;
+            for pos in start..start + w {
+                let forward = match MMer5::from_bytes(&seq[pos..]) {
+                    Ok(k) => k,
+                    Err(_) => {
+                        best = None;
+                        break;
+                    }
+                };
+                let canonical = forward.get_canonical();
+                let strand = if canonical == forward {
+                    Strand::Forward
+                } else {
+                    Strand::Reverse
+                };
+                let key = minimizers.key(canonical, strand);
+                if !matches!(best, Some((_, b)) if b <= key) {
+                    best = Some((pos, key));
+                }
+            }
+            if let Some((pos, (_, mmer))) = best {
+                ret.push((start, pos, mmer));
+            }
+        }
+        ret
+    }
+
+    #[test]
+    pub fn test_minimizers() {
+        let seq = random_seq(2000);
+        for &order in &[
+            MinimizerOrder::Lexicographic,
+            MinimizerOrder::Canonical,
+            MinimizerOrder::Hash(42),
+        ] {
+            for &w in &[1, 4, 11] {
+                let fast: Vec<_> = Minimizers::<MMer5>::new(&seq, w, order).collect();
+                assert_eq!(fast, brute_force(&seq, w, order), "{:?} w={}", order, w);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_minimizer_strand() {
+        let seq = b"ACGTTGCAGGCATTTCAGCTAGCTAGGATCG";
+        let rc = reverse_complement(seq);
+        let order = MinimizerOrder::Hash(7);
+        let forward: Vec<_> = Minimizers::<MMer5>::new(seq, 27, order).collect();
+        let reverse: Vec<_> = Minimizers::<MMer5>::new(&rc, 27, order).collect();
+        assert_eq!(forward[0].2, reverse[0].2);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::kmer::AbstractKMer;
+use crate::kmer::Alphabet;
+
+/// Largest Hamming distance `Neighbors` enumerates.
+pub const MAX_HAMMING_DISTANCE: usize = 8;
+// units fit in 5 bits for every alphabet we have
+const MAX_UNITS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Hamming,
+    // (position, index of the unit shifted in)
+    Deletion(usize, usize),
+    Insertion(usize, usize),
+    Done,
+}
+
+/// Iterates the k-mers within a Hamming distance of a k-mer, the k-mer
+/// itself included, and with `with_indels` also those one insertion or
+/// deletion away. Indels keep the k-mer anchored at its first base: a
+/// deletion shifts a base in on the right, an insertion drops the last base.
+///
+/// Nothing is allocated. Without `dedup` a k-mer reachable in several ways,
+/// e.g. by deleting either base of a homopolymer run, comes up once per way;
+/// with `canonical` the canonical forms are yielded instead.
+pub struct Neighbors<K> {
+    center: K,
+    max_distance: usize,
+    indels: bool,
+    canonical: bool,
+    dedup: bool,
+    units: [u8; MAX_UNITS], // valid units in increasing order
+    unit_count: usize,
+    phase: Phase,
+    // Hamming distance, substituted positions and the alternative each takes
+    distance: usize,
+    positions: [usize; MAX_HAMMING_DISTANCE],
+    alternatives: [usize; MAX_HAMMING_DISTANCE],
+}
+
+impl<K> Neighbors<K>
+where
+    K: AbstractKMer,
+{
+    pub fn new(center: K, max_distance: usize) -> Self {
+        assert!(max_distance <= MAX_HAMMING_DISTANCE);
+        assert!(K::Alphabet::UNIT_LEN <= 5, "too many units to enumerate");
+        let mut units = [0; MAX_UNITS];
+        let mut unit_count = 0;
+        for unit in 0..1u8 << K::Alphabet::UNIT_LEN {
+            if K::Alphabet::to_u8(unit).is_some() {
+                units[unit_count] = unit;
+                unit_count += 1;
+            }
+        }
+        Neighbors {
+            center,
+            max_distance: max_distance.min(K::kmer_len()),
+            indels: false,
+            canonical: false,
+            dedup: false,
+            units,
+            unit_count,
+            phase: Phase::Hamming,
+            distance: 0,
+            positions: [0; MAX_HAMMING_DISTANCE],
+            alternatives: [0; MAX_HAMMING_DISTANCE],
+        }
+    }
+
+    pub fn with_indels(mut self) -> Self {
+        self.indels = true;
+        self
+    }
+
+    pub fn canonical(mut self) -> Self {
+        self.canonical = true;
+        self
+    }
+
+    pub fn dedup(mut self) -> Self {
+        self.dedup = true;
+        self
+    }
+
+    // the `alternative`-th valid unit other than `unit`
+    fn alternative(&self, unit: u8, alternative: usize) -> u8 {
+        let skip = self.units[..self.unit_count]
+            .iter()
+            .position(|&u| u == unit)
+            .is_some_and(|i| i <= alternative);
+        self.units[alternative + skip as usize]
+    }
+
+    fn hamming_candidate(&self) -> K {
+        let mut kmer = self.center;
+        for j in 0..self.distance {
+            let at = self.positions[j];
+            kmer = kmer.with_unit(
+                at,
+                self.alternative(self.center.unit(at), self.alternatives[j]),
+            );
+        }
+        kmer
+    }
+
+    // moves to the next Hamming neighbor, false when there is none
+    fn advance_hamming(&mut self) -> bool {
+        let k = K::kmer_len();
+        for j in 0..self.distance {
+            self.alternatives[j] += 1;
+            if self.alternatives[j] < self.unit_count - 1 {
+                return true;
+            }
+            self.alternatives[j] = 0;
+        }
+        for j in (0..self.distance).rev() {
+            if self.positions[j] < k - self.distance + j {
+                self.positions[j] += 1;
+                for l in j + 1..self.distance {
+                    self.positions[l] = self.positions[l - 1] + 1;
+                }
+                return true;
+            }
+        }
+        self.distance += 1;
+        if self.distance > self.max_distance || self.unit_count < 2 {
+            return false;
+        }
+        for j in 0..self.distance {
+            self.positions[j] = j;
+            self.alternatives[j] = 0;
+        }
+        true
+    }
+
+    fn hamming(&self, kmer: &K) -> usize {
+        (0..K::kmer_len())
+            .filter(|&i| kmer.unit(i) != self.center.unit(i))
+            .count()
+    }
+
+    // whether `kmer` is the center with one base deleted and one shifted in
+    fn is_deletion(&self, kmer: &K) -> bool {
+        let k = K::kmer_len();
+        let prefix = (0..k - 1)
+            .take_while(|&i| kmer.unit(i) == self.center.unit(i))
+            .count();
+        let suffix = (0..k - 1)
+            .rev()
+            .take_while(|&i| kmer.unit(i) == self.center.unit(i + 1))
+            .count();
+        prefix + suffix >= k - 1
+    }
+
+    // whether `kmer` is the center with one base inserted and the last dropped
+    fn is_insertion(&self, kmer: &K) -> bool {
+        let k = K::kmer_len();
+        let prefix = (0..k)
+            .take_while(|&i| kmer.unit(i) == self.center.unit(i))
+            .count();
+        let suffix = (1..k)
+            .rev()
+            .take_while(|&i| kmer.unit(i) == self.center.unit(i - 1))
+            .count();
+        prefix + suffix >= k - 1
+    }
+
+    fn contains(&self, kmer: &K) -> bool {
+        self.hamming(kmer) <= self.max_distance
+            || self.indels && (self.is_deletion(kmer) || self.is_insertion(kmer))
+    }
+
+    fn accept(&self, kmer: K, phase: Phase) -> Option<K> {
+        if self.dedup {
+            let seen = match phase {
+                Phase::Deletion(..) => self.hamming(&kmer) <= self.max_distance,
+                Phase::Insertion(..) => {
+                    self.hamming(&kmer) <= self.max_distance || self.is_deletion(&kmer)
+                }
+                _ => false,
+            };
+            if seen {
+                return None;
+            }
+        }
+        if !self.canonical || !K::has_complement() {
+            return Some(kmer);
+        }
+        let rc = kmer.reverse().complement();
+        if rc >= kmer {
+            Some(kmer)
+        } else if self.dedup && self.contains(&rc) {
+            // the neighbor `rc` yields this canonical k-mer
+            None
+        } else {
+            Some(rc)
+        }
+    }
+}
+
+impl<K> Iterator for Neighbors<K>
+where
+    K: AbstractKMer,
+{
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        let k = K::kmer_len();
+        loop {
+            let phase = self.phase;
+            let candidate = match phase {
+                Phase::Hamming => {
+                    let kmer = self.hamming_candidate();
+                    if !self.advance_hamming() {
+                        self.phase = if self.indels {
+                            Phase::Deletion(0, 0)
+                        } else {
+                            Phase::Done
+                        };
+                    }
+                    Some(kmer)
+                }
+                Phase::Deletion(at, unit) => {
+                    self.phase = match (unit + 1 < self.unit_count, at + 1 < k) {
+                        (true, _) => Phase::Deletion(at, unit + 1),
+                        (false, true) => Phase::Deletion(at + 1, 0),
+                        (false, false) => Phase::Insertion(0, 0),
+                    };
+                    // deleting any base of a run gives the same k-mer
+                    if self.dedup && at > 0 && self.center.unit(at - 1) == self.center.unit(at) {
+                        None
+                    } else {
+                        let mut kmer = self.center;
+                        for i in at..k - 1 {
+                            kmer = kmer.with_unit(i, self.center.unit(i + 1));
+                        }
+                        Some(kmer.with_unit(k - 1, self.units[unit]))
+                    }
+                }
+                Phase::Insertion(at, unit) => {
+                    self.phase = match (unit + 1 < self.unit_count, at + 1 < k) {
+                        (true, _) => Phase::Insertion(at, unit + 1),
+                        (false, true) => Phase::Insertion(at + 1, 0),
+                        (false, false) => Phase::Done,
+                    };
+                    // inserting a base next to a copy of it
+                    if self.dedup && at > 0 && self.center.unit(at - 1) == self.units[unit] {
+                        None
+                    } else {
+                        let mut kmer = self.center;
+                        for i in (at + 1..k).rev() {
+                            kmer = kmer.with_unit(i, self.center.unit(i - 1));
+                        }
+                        Some(kmer.with_unit(at, self.units[unit]))
+                    }
+                }
+                Phase::Done => return None,
+            };
+            if let Some(kmer) = candidate.and_then(|kmer| self.accept(kmer, phase)) {
+                return Some(kmer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::KMeru64;
+    use crate::kmer::Protein;
+    use crate::kmer::DNA;
+    use std::collections::BTreeSet;
+
+    type KMer6 = KMeru64<DNA, 6>;
+
+    fn all_kmers() -> impl Iterator<Item = KMer6> {
+        (0..1u128 << 12).map(KMer6::from_u128)
+    }
+
+    fn indel_neighbors(center: &KMer6) -> BTreeSet<KMer6> {
+        let bases = center.to_string().into_bytes();
+        let mut ret = BTreeSet::new();
+        for i in 0..6 {
+            for &c in b"ACGT" {
+                let mut deleted = bases.clone();
+                deleted.remove(i);
+                deleted.push(c);
+                ret.insert(KMer6::from_bytes(&deleted).unwrap());
+                let mut inserted = bases.clone();
+                inserted.insert(i, c);
+                ret.insert(KMer6::from_bytes(&inserted).unwrap());
+            }
+        }
+        ret
+    }
+
+    fn hamming(a: &KMer6, b: &KMer6) -> usize {
+        (0..6).filter(|&i| a.unit(i) != b.unit(i)).count()
+    }
+
+    #[test]
+    pub fn test_hamming_neighbors() {
+        let center = "ACGGTA".parse::<KMer6>().unwrap();
+        for d in 0..=3 {
+            let neighbors: Vec<_> = Neighbors::new(center, d).collect();
+            let expected: BTreeSet<_> = all_kmers().filter(|k| hamming(k, &center) <= d).collect();
+            // substitutions alone never repeat a k-mer
+            assert_eq!(neighbors.len(), expected.len());
+            assert_eq!(neighbors.into_iter().collect::<BTreeSet<_>>(), expected);
+        }
+
+        let peptide = "MKV".parse::<KMeru64<Protein, 3>>().unwrap();
+        assert_eq!(Neighbors::new(peptide, 1).count(), 1 + 3 * 19);
+    }
+
+    #[test]
+    pub fn test_indel_neighbors() {
+        for center in &["ACGGTA", "AAAAAA", "ACACAC", "GATTCA"] {
+            let center = center.parse::<KMer6>().unwrap();
+            let mut expected: BTreeSet<_> =
+                all_kmers().filter(|k| hamming(k, &center) <= 1).collect();
+            expected.extend(indel_neighbors(&center));
+
+            let neighbors: Vec<_> = Neighbors::new(center, 1).with_indels().dedup().collect();
+            let unique: BTreeSet<_> = neighbors.iter().copied().collect();
+            assert_eq!(neighbors.len(), unique.len(), "{}", center);
+            assert_eq!(unique, expected);
+
+            let repeated: BTreeSet<_> = Neighbors::new(center, 1).with_indels().collect();
+            assert_eq!(repeated, expected);
+
+            let canonical: Vec<_> = Neighbors::new(center, 1)
+                .with_indels()
+                .canonical()
+                .dedup()
+                .collect();
+            let unique: BTreeSet<_> = canonical.iter().copied().collect();
+            assert_eq!(canonical.len(), unique.len(), "{}", center);
+            let expected: BTreeSet<_> = expected.iter().map(|k| k.get_canonical()).collect();
+            assert_eq!(unique, expected);
+        }
+    }
+}
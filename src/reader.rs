@@ -0,0 +1,768 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::io::BufRead;
+
+/// Reads the next line into `buf` without its line ending, false at the end
+/// of the input.
+fn read_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<bool> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(false);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+    Ok(true)
+}
+
+// UTF-8 byte order mark, which some editors put before the first record
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+fn first_word(header: &[u8]) -> &[u8] {
+    let end = header
+        .iter()
+        .position(|c| c.is_ascii_whitespace())
+        .unwrap_or(header.len());
+    &header[..end]
+}
+
+/// Why FASTQ input could not be parsed, with the 1-based line it was noticed
+/// on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FastqError {
+    /// A record does not start with an `@` header.
+    MissingHeader { line: usize },
+    /// A new header came before the `+` line of the record.
+    MissingSeparator { line: usize },
+    /// The `+` line repeats a header other than the record's.
+    HeaderMismatch { line: usize },
+    /// The quality is longer, or ends shorter, than the sequence.
+    LengthMismatch {
+        line: usize,
+        seq_len: usize,
+        qual_len: usize,
+    },
+    /// The input ends before the `+` line of the record.
+    Truncated { line: usize },
+    /// The sequence of a record goes on over a second line, which
+    /// `FastqReader::unwrapped` rejects.
+    Wrapped { line: usize },
+}
+
+impl FastqError {
+    pub fn line(&self) -> usize {
+        match *self {
+            FastqError::MissingHeader { line }
+            | FastqError::MissingSeparator { line }
+            | FastqError::HeaderMismatch { line }
+            | FastqError::LengthMismatch { line, .. }
+            | FastqError::Truncated { line }
+            | FastqError::Wrapped { line } => line,
+        }
+    }
+}
+
+impl fmt::Display for FastqError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line())?;
+        match *self {
+            FastqError::MissingHeader { .. } => write!(f, "expected a record starting with '@'"),
+            FastqError::MissingSeparator { .. } => {
+                write!(f, "expected a '+' line before the next record")
+            }
+            FastqError::HeaderMismatch { .. } => {
+                write!(f, "the '+' line does not repeat the record header")
+            }
+            FastqError::LengthMismatch {
+                seq_len, qual_len, ..
+            } => write!(
+                f,
+                "{} quality values for a sequence of {} bases",
+                qual_len, seq_len
+            ),
+            FastqError::Truncated { .. } => write!(f, "the input ends inside a record"),
+            FastqError::Wrapped { .. } => write!(f, "a record is wrapped over several lines"),
+        }
+    }
+}
+
+impl std::error::Error for FastqError {}
+
+impl From<FastqError> for io::Error {
+    fn from(e: FastqError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FastqRecord {
+    /// The header line without its `@`.
+    pub header: Vec<u8>,
+    pub seq: Vec<u8>,
+    pub qual: Vec<u8>,
+}
+
+impl FastqRecord {
+    /// The header up to the first whitespace.
+    pub fn id(&self) -> &[u8] {
+        first_word(&self.header)
+    }
+}
+
+/// Parses FASTQ records as header, sequence, `+` and quality lines. The
+/// sequence runs up to the `+` line and the quality until it is as long as
+/// the sequence, so quality lines starting with `@` or `+` and records
+/// wrapped over several lines both read right. Stops at the first error.
+pub struct FastqReader<R> {
+    reader: R,
+    line: usize,
+    buf: Vec<u8>,
+    wrapped: bool,
+    failed: bool,
+}
+
+impl<R: BufRead> FastqReader<R> {
+    pub fn new(reader: R) -> Self {
+        FastqReader {
+            reader,
+            line: 0,
+            buf: vec![],
+            wrapped: true,
+            failed: false,
+        }
+    }
+
+    /// Reads only records of four lines, failing on wrapped sequences and
+    /// taking the quality from one line, as input split by `split_input`
+    /// has to be.
+    pub fn unwrapped(reader: R) -> Self {
+        FastqReader {
+            wrapped: false,
+            ..Self::new(reader)
+        }
+    }
+
+    /// Number of lines read so far.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn next_line(&mut self) -> io::Result<bool> {
+        let more = read_line(&mut self.reader, &mut self.buf)?;
+        if more {
+            self.line += 1;
+        }
+        Ok(more)
+    }
+
+    /// Reads the next record into `record`, reusing its buffers, false at
+    /// the end of the input.
+    pub fn read_record(&mut self, record: &mut FastqRecord) -> io::Result<bool> {
+        if self.failed {
+            return Ok(false);
+        }
+        let read = self.parse_record(record);
+        self.failed = read.is_err();
+        read
+    }
+
+    fn parse_record(&mut self, record: &mut FastqRecord) -> io::Result<bool> {
+        // blank lines between records are fine
+        loop {
+            if !self.next_line()? {
+                return Ok(false);
+            }
+            if !self.buf.is_empty() {
+                break;
+            }
+        }
+        if self.buf[0] != b'@' {
+            return Err(FastqError::MissingHeader { line: self.line }.into());
+        }
+        record.header.clear();
+        record.header.extend_from_slice(&self.buf[1..]);
+        record.seq.clear();
+        record.qual.clear();
+
+        let mut lines = 0;
+        loop {
+            if !self.next_line()? {
+                return Err(FastqError::Truncated { line: self.line }.into());
+            }
+            match self.buf.first() {
+                Some(b'+') => break,
+                Some(b'@') => return Err(FastqError::MissingSeparator { line: self.line }.into()),
+                _ => record.seq.extend_from_slice(&self.buf),
+            }
+            lines += 1;
+            if lines > 1 && !self.wrapped {
+                return Err(FastqError::Wrapped { line: self.line }.into());
+            }
+        }
+        if self.buf.len() > 1 && self.buf[1..] != record.header[..] {
+            return Err(FastqError::HeaderMismatch { line: self.line }.into());
+        }
+
+        // at least one line, an empty sequence still has an empty quality
+        loop {
+            let more = self.next_line()?;
+            if more {
+                record.qual.extend_from_slice(&self.buf);
+            }
+            if !more || record.qual.len() >= record.seq.len() || !self.wrapped {
+                break;
+            }
+        }
+        if record.qual.len() != record.seq.len() {
+            return Err(FastqError::LengthMismatch {
+                line: self.line,
+                seq_len: record.seq.len(),
+                qual_len: record.qual.len(),
+            }
+            .into());
+        }
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for FastqReader<R> {
+    type Item = io::Result<FastqRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = FastqRecord::default();
+        match self.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Bases of a FASTA record `SeqReader::chunked` hands out at a time, give
+/// or take a line.
+pub const FASTA_CHUNK_LEN: usize = 1 << 16;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FastaRecord {
+    /// The header line without its `>`, empty for sequence before the first
+    /// header.
+    pub header: Vec<u8>,
+    pub seq: Vec<u8>,
+    /// Where `seq` starts in the record, nonzero for all but the first piece
+    /// of a chunked record.
+    pub offset: usize,
+}
+
+impl FastaRecord {
+    /// The header up to the first whitespace.
+    pub fn id(&self) -> &[u8] {
+        first_word(&self.header)
+    }
+}
+
+/// Parses FASTA records, joining the lines a sequence is wrapped over.
+/// Chunked, long records come out in pieces that overlap by a fixed number
+/// of bases, so a k-mer no longer than the overlap plus one sits in exactly
+/// one piece and whole chromosomes are never held at once.
+pub struct FastaReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    max_len: usize,
+    overlap: usize,
+    // the record being read, `seq` holding its bases from `offset` on
+    open: bool,
+    header: Vec<u8>,
+    next_header: Option<Vec<u8>>,
+    seq: Vec<u8>,
+    offset: usize,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    /// Reads whole records.
+    pub fn new(reader: R) -> Self {
+        Self::chunked(reader, usize::MAX, 0)
+    }
+
+    /// Reads records in pieces of at least `max_len` bases, but for the last
+    /// of each record, the next starting `overlap` bases before the end of
+    /// the previous.
+    pub fn chunked(reader: R, max_len: usize, overlap: usize) -> Self {
+        assert!(overlap < max_len, "pieces must be longer than the overlap");
+        FastaReader {
+            reader,
+            buf: vec![],
+            max_len,
+            overlap,
+            open: false,
+            header: vec![],
+            next_header: None,
+            seq: vec![],
+            offset: 0,
+        }
+    }
+
+    fn open_record(&mut self) -> io::Result<bool> {
+        self.seq.clear();
+        self.offset = 0;
+        match self.next_header.take() {
+            Some(header) => self.header = header,
+            None => loop {
+                if !read_line(&mut self.reader, &mut self.buf)? {
+                    return Ok(false);
+                }
+                match self.buf.first() {
+                    None => continue,
+                    Some(b'>') => self.header = self.buf[1..].to_vec(),
+                    Some(_) => {
+                        self.header.clear();
+                        self.seq.extend_from_slice(&self.buf);
+                    }
+                }
+                break;
+            },
+        }
+        self.open = true;
+        Ok(true)
+    }
+
+    /// Reads the next record, or piece of one, into `record`, reusing its
+    /// buffers, false at the end of the input.
+    pub fn read_record(&mut self, record: &mut FastaRecord) -> io::Result<bool> {
+        loop {
+            if !self.open && !self.open_record()? {
+                return Ok(false);
+            }
+            let mut ended = false;
+            while self.seq.len() < self.max_len {
+                if !read_line(&mut self.reader, &mut self.buf)? {
+                    ended = true;
+                    break;
+                }
+                if self.buf.first() == Some(&b'>') {
+                    self.next_header = Some(self.buf[1..].to_vec());
+                    ended = true;
+                    break;
+                }
+                self.seq.extend_from_slice(&self.buf);
+            }
+            // a last piece inside the overlap holds nothing new
+            if ended && self.offset > 0 && self.seq.len() <= self.overlap {
+                self.open = false;
+                continue;
+            }
+            record.header.clone_from(&self.header);
+            record.seq.clone_from(&self.seq);
+            record.offset = self.offset;
+            if ended {
+                self.open = false;
+            } else {
+                let done = self.seq.len() - self.overlap;
+                self.seq.drain(..done);
+                self.offset += done;
+            }
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = io::Result<FastaRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = FastaRecord::default();
+        match self.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+enum Format<R> {
+    Fasta(FastaReader<R>),
+    Fastq(FastqReader<R>),
+}
+
+/// Yields the sequences of FASTA or FASTQ input, told apart by its first
+/// byte after a byte order mark and blank lines.
+pub struct SeqReader<R> {
+    format: Format<R>,
+}
+
+impl<R: BufRead> SeqReader<R> {
+    /// Yields whole records.
+    pub fn new(reader: R) -> io::Result<Self> {
+        Self::open(reader, FastaReader::new)
+    }
+
+    /// Yields FASTA records longer than `FASTA_CHUNK_LEN` in pieces
+    /// overlapping by `overlap` bases, see `FastaReader::chunked`.
+    pub fn chunked(reader: R, overlap: usize) -> io::Result<Self> {
+        Self::open(reader, |reader| {
+            FastaReader::chunked(reader, FASTA_CHUNK_LEN.max(overlap + 1), overlap)
+        })
+    }
+
+    /// Fails on FASTQ records wrapped over several lines, which `split_input`
+    /// cannot split correctly.
+    pub fn unwrapped(self) -> Self {
+        let format = match self.format {
+            Format::Fastq(reader) => Format::Fastq(FastqReader {
+                wrapped: false,
+                ..reader
+            }),
+            format => format,
+        };
+        SeqReader { format }
+    }
+
+    fn open<F: FnOnce(R) -> FastaReader<R>>(mut reader: R, fasta: F) -> io::Result<Self> {
+        // a byte order mark and blank lines may come before the first record
+        if reader.fill_buf()?.starts_with(BOM) {
+            reader.consume(BOM.len());
+        }
+        let mut lines = 0;
+        let first = loop {
+            let buf = reader.fill_buf()?;
+            let blank = buf
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(buf.len());
+            lines += memchr::memchr_iter(b'\n', &buf[..blank]).count();
+            let first = buf.get(blank).copied();
+            reader.consume(blank);
+            if first.is_some() || blank == 0 {
+                break first;
+            }
+        };
+        let format = match first {
+            Some(b'@') => Format::Fastq(FastqReader {
+                line: lines,
+                ..FastqReader::new(reader)
+            }),
+            Some(b'>') | None => Format::Fasta(fasta(reader)),
+            Some(byte) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "line {}: expected a FASTA '>' or FASTQ '@' record, found {:?}",
+                        lines + 1,
+                        byte as char
+                    ),
+                ))
+            }
+        };
+        Ok(SeqReader { format })
+    }
+}
+
+impl<R: BufRead> Iterator for SeqReader<R> {
+    type Item = io::Result<Vec<u8>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.format {
+            Format::Fasta(reader) => Some(reader.next()?.map(|record| record.seq)),
+            Format::Fastq(reader) => Some(reader.next()?.map(|record| record.seq)),
+        }
+    }
+}
+
+/// Iterates the lines of a buffer, without their `\n`.
+pub struct Lines<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Lines<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Lines { data }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let (line, rest) = match memchr::memchr(b'\n', self.data) {
+            Some(pos) => (&self.data[..pos], &self.data[pos + 1..]),
+            None => (self.data, &self.data[self.data.len()..]),
+        };
+        self.data = rest;
+        Some(line)
+    }
+}
+
+// A FASTQ record is taken to start at an `@` line whose next but one line
+// starts with `+` and whose sequence and quality are equally long, which a
+// quality line starting with `@` cannot pass as the sequence never starts
+// with `+`. Wrapped FASTQ records are not told apart, so split input must
+// not have them, see `SeqReader::unwrapped`.
+fn is_record_start(rest: &[u8], fastq: bool) -> bool {
+    if !fastq {
+        return rest.first() == Some(&b'>');
+    }
+    let mut lines = Lines::new(rest);
+    match (lines.next(), lines.next(), lines.next(), lines.next()) {
+        (Some(header), Some(seq), Some(plus), Some(qual)) => {
+            header.starts_with(b"@") && plus.starts_with(b"+") && seq.len() == qual.len()
+        }
+        _ => false,
+    }
+}
+
+/// Whether the input starting with `head` is FASTQ, as `SeqReader` tells.
+pub fn is_fastq(head: &[u8]) -> bool {
+    let head = head.strip_prefix(BOM).unwrap_or(head);
+    head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'@')
+}
+
+/// Start of the first FASTA or FASTQ record at or after byte `from` of the
+/// input `data`, its length if there is none.
+pub fn next_record_start(data: &[u8], from: usize) -> usize {
+    find_record_start(data, from, is_fastq(data))
+}
+
+/// `next_record_start` in part of the input, FASTQ or not as the whole is.
+pub(crate) fn find_record_start(data: &[u8], from: usize, fastq: bool) -> usize {
+    let mut pos = from;
+    while pos < data.len() {
+        if (pos == 0 || data[pos - 1] == b'\n') && is_record_start(&data[pos..], fastq) {
+            return pos;
+        }
+        pos = memchr::memchr(b'\n', &data[pos..]).map_or(data.len(), |i| pos + i + 1);
+    }
+    data.len()
+}
+
+/// The `part`th of `parts` byte ranges of about equal size the input `data`
+/// splits into at record starts, so each record is in exactly one range.
+/// FASTQ records have to be four lines each for this.
+pub fn split_input(data: &[u8], part: usize, parts: usize) -> &[u8] {
+    assert!(part < parts);
+    let boundary = |i: usize| match i {
+        0 => 0,
+        i if i == parts => data.len(),
+        i => next_record_start(data, data.len() / parts * i),
+    };
+    &data[boundary(part)..boundary(part + 1)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<io::Result<FastqRecord>> {
+        FastqReader::new(input.as_bytes()).collect()
+    }
+
+    fn error_of(input: &str) -> FastqError {
+        let err = parse(input).pop().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        *err.get_ref().unwrap().downcast_ref::<FastqError>().unwrap()
+    }
+
+    #[test]
+    pub fn test_fastq_reader() {
+        // qualities starting with '@' and '+', CRLF, a blank line and an
+        // empty read between the records
+        let input = "@r1 first read\r\nACGT\r\n+\r\n@III\r\n\n@r2\nGGC\n+r2\n+II\n@r3\n\n+\n\n";
+        let records: Vec<_> = parse(input).into_iter().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].header, b"r1 first read");
+        assert_eq!(records[0].id(), b"r1");
+        assert_eq!(records[0].seq, b"ACGT");
+        assert_eq!(records[0].qual, b"@III");
+        assert_eq!(records[1].seq, b"GGC");
+        assert_eq!(records[1].qual, b"+II");
+        assert!(records[2].seq.is_empty() && records[2].qual.is_empty());
+
+        // records wrapped over several lines
+        let input = "@r1\nACGT\nAC\n+\n@@@\n+++\n@r2\nT\n+\nI";
+        let records: Vec<_> = parse(input).into_iter().map(Result::unwrap).collect();
+        assert_eq!(records[0].seq, b"ACGTAC");
+        assert_eq!(records[0].qual, b"@@@+++");
+        assert_eq!(records[1].seq, b"T");
+
+        let mut reader = FastqReader::new(&b"@r1\nAC\n+\nII\n@r2\nGT\n+\nII\n"[..]);
+        let mut record = FastqRecord::default();
+        assert!(reader.read_record(&mut record).unwrap());
+        assert!(reader.read_record(&mut record).unwrap());
+        assert_eq!(record.seq, b"GT");
+        assert!(!reader.read_record(&mut record).unwrap());
+        assert_eq!(reader.line(), 8);
+    }
+
+    #[test]
+    pub fn test_fastq_errors() {
+        let ok = "@r1\nACGT\n+\nIIII\n";
+        assert_eq!(
+            error_of(&format!("{}ACGT\n", ok)),
+            FastqError::MissingHeader { line: 5 }
+        );
+        assert_eq!(
+            error_of(&format!("{}@r2\nACGT\n@r3\n", ok)),
+            FastqError::MissingSeparator { line: 7 }
+        );
+        assert_eq!(
+            error_of("@r1\nACGT\n+r2\nIIII\n"),
+            FastqError::HeaderMismatch { line: 3 }
+        );
+        assert_eq!(
+            error_of("@r1\nACGT\n+\nIIIII\n"),
+            FastqError::LengthMismatch {
+                line: 4,
+                seq_len: 4,
+                qual_len: 5
+            }
+        );
+        assert_eq!(
+            error_of(&format!("{}@r2\nACGT\n+\nII", ok)),
+            FastqError::LengthMismatch {
+                line: 8,
+                seq_len: 4,
+                qual_len: 2
+            }
+        );
+        assert_eq!(
+            error_of(&format!("{}@r2\nACGT", ok)),
+            FastqError::Truncated { line: 6 }
+        );
+        assert_eq!(
+            FastqError::Truncated { line: 6 }.to_string(),
+            "line 6: the input ends inside a record"
+        );
+        let wrapped = "@r1\nACGT\nAC\n+\nIIII\nII\n";
+        let err = FastqReader::unwrapped(wrapped.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            *err.get_ref().unwrap().downcast_ref::<FastqError>().unwrap(),
+            FastqError::Wrapped { line: 3 }
+        );
+        assert!(FastqReader::unwrapped("@r1\nACGT\n+\nIIII\n".as_bytes())
+            .next()
+            .unwrap()
+            .is_ok());
+        // nothing is read past an error
+        assert_eq!(parse("ACGT\n@r1\nA\n+\nI\n").len(), 1);
+    }
+
+    #[test]
+    pub fn test_fasta_reader() {
+        let input = "ACG\n>chr1 first\r\nACGT\r\nAC\r\n\r\nGT\n>chr2\n>chr3\nT";
+        let records: Vec<_> = FastaReader::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert!(records[0].header.is_empty());
+        assert_eq!(records[0].seq, b"ACG");
+        assert_eq!(records[1].id(), b"chr1");
+        assert_eq!(records[1].seq, b"ACGTACGT");
+        assert!(records[2].seq.is_empty());
+        assert_eq!(records[3].seq, b"T");
+
+        // the pieces of a record hold each k-mer once
+        let seq: Vec<u8> = (0..1000).map(|i| b"ACGT"[(i * 7 + i / 13) % 4]).collect();
+        let wrapped: Vec<u8> = seq
+            .chunks(60)
+            .flat_map(|line| [line, b"\n"].concat())
+            .collect();
+        let input = [&b">chr1\n"[..], &wrapped, b">chr2\n", &wrapped].concat();
+        let k = 21;
+        let expected: Vec<_> = [b"chr1", b"chr2"]
+            .iter()
+            .flat_map(|id| (0..=seq.len() - k).map(move |pos| (id.to_vec(), pos)))
+            .collect();
+        for max_len in [k, 50, 100, 999, 1000, 1001] {
+            let mut kmers = vec![];
+            for record in FastaReader::chunked(&input[..], max_len, k - 1) {
+                let record = record.unwrap();
+                let end = record.offset + record.seq.len();
+                assert_eq!(record.seq, seq[record.offset..end]);
+                for pos in record.offset..(end + 1).saturating_sub(k) {
+                    kmers.push((record.header.clone(), pos));
+                }
+            }
+            assert_eq!(kmers, expected);
+        }
+    }
+
+    #[test]
+    pub fn test_split_input() {
+        let lines: Vec<&[u8]> = Lines::new(b"a\n\nbc\nd").collect();
+        assert_eq!(lines, vec![&b"a"[..], b"", b"bc", b"d"]);
+
+        // qualities starting with '@' and '+' look like headers and separators
+        let fastq: Vec<String> = (0..50)
+            .map(|i| format!("@r{}\nACGTA\n+\n{}IIII\n", i, ["@", "+", "I"][i % 3]))
+            .collect();
+        let fasta: Vec<String> = (0..50)
+            .map(|i| format!(">chr{}\nACGT\nACG\n\n", i))
+            .collect();
+        for records in [fastq, fasta] {
+            let data = records.concat().into_bytes();
+            assert_eq!(next_record_start(&data, 0), 0);
+            assert_eq!(next_record_start(&data, 1), records[0].len());
+            for parts in 1..10 {
+                let mut joined = vec![];
+                let mut reads = 0;
+                for part in 0..parts {
+                    let share = split_input(&data, part, parts);
+                    joined.extend_from_slice(share);
+                    reads += SeqReader::new(share).unwrap().map(Result::unwrap).count();
+                }
+                assert_eq!(joined, data);
+                assert_eq!(reads, 50);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_seq_reader() {
+        let fastq = "@r1\nACGT\n+\n@III\n@r2\nGG\n+\n+I\n";
+        let reads: Vec<_> = SeqReader::new(fastq.as_bytes())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reads, vec![b"ACGT".to_vec(), b"GG".to_vec()]);
+        let fasta = ">chr1\nAC\nGT\n\n>chr2\nGG\n";
+        let reads: Vec<_> = SeqReader::new(fasta.as_bytes())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reads, vec![b"ACGT".to_vec(), b"GG".to_vec()]);
+        assert!(SeqReader::new(&b"@r1\nAC\n+\nI\n"[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_err());
+
+        // a byte order mark and blank lines before the first record
+        let reads: Vec<_> = SeqReader::new(&b"\xef\xbb\xbf\n \r\n@r1\nAC\n+\nII\n"[..])
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reads, vec![b"AC".to_vec()]);
+        assert!(is_fastq(b"\xef\xbb\xbf\n@r1"));
+        let reads: Vec<_> = SeqReader::new(&b"\n\n>chr1\nAC\n"[..])
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reads, vec![b"AC".to_vec()]);
+        let err = SeqReader::new(&b"\n@r1\nAC\n+\nI\n"[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("line 5:"));
+        let err = SeqReader::new(&b"\nACGT\n"[..]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "line 2: expected a FASTA '>' or FASTQ '@' record, found 'A'"
+        );
+        assert_eq!(SeqReader::new(&b" \n"[..]).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::kmer::AbstractKMer;
+use crate::kmer::CanonicalKmers;
+use crate::minimizer::hash_kmer;
+use crate::minimizer::MinimizerOrder;
+use crate::minimizer::Minimizers;
+
+// strand independent, so a syncmer is one on both strands
+const SMER_ORDER: MinimizerOrder = MinimizerOrder::Hash(0);
+const STROBE_SEED: u64 = 0;
+
+/// s-mer length the binaries pick syncmers by for k-mers of length `k`.
+pub const fn syncmer_smer_len(k: usize) -> usize {
+    if k > 4 {
+        k - 4
+    } else {
+        1
+    }
+}
+
+/// Which k-mers are syncmers, by the offset of their smallest s-mer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncmerKind {
+    /// The smallest s-mer sits at the given offset. Only the middle offset
+    /// picks the same k-mers on both strands.
+    Open(usize),
+    /// The smallest s-mer sits at the start or the end.
+    Closed,
+}
+
+/// Seeds counted instead of every k-mer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    Syncmer(SyncmerKind),
+    /// Randstrobes with the second strobe starting this many bases after
+    /// the first, inclusive.
+    Randstrobe(usize, usize),
+}
+
+/// Iterates `(position, canonical k-mer)` over the syncmers of a sequence,
+/// with the s-mers `S` ordered by a seeded hash of their canonical form.
+pub struct Syncmers<'a, K, S> {
+    kmers: CanonicalKmers<'a, K>,
+    smers: Minimizers<'a, S>,
+    kind: SyncmerKind,
+}
+
+impl<'a, K, S> Syncmers<'a, K, S>
+where
+    K: AbstractKMer,
+    S: AbstractKMer<Alphabet = K::Alphabet>,
+{
+    pub fn new(seq: &'a [u8], kind: SyncmerKind) -> Self {
+        assert!(
+            S::kmer_len() <= K::kmer_len(),
+            "s-mers are longer than the k-mers"
+        );
+        Syncmers {
+            kmers: CanonicalKmers::new(seq),
+            smers: Minimizers::new(seq, K::kmer_len() - S::kmer_len() + 1, SMER_ORDER),
+            kind,
+        }
+    }
+}
+
+impl<'a, K, S> Iterator for Syncmers<'a, K, S>
+where
+    K: AbstractKMer,
+    S: AbstractKMer<Alphabet = K::Alphabet>,
+{
+    type Item = (usize, K);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (pos, kmer, _) = self.kmers.next()?;
+            // a window of s-mers is valid exactly when its k-mer is
+            let (window, smer_pos, _) = self.smers.next().unwrap();
+            debug_assert_eq!(pos, window);
+            let offset = smer_pos - pos;
+            let is_syncmer = match self.kind {
+                SyncmerKind::Open(t) => offset == t,
+                SyncmerKind::Closed => offset == 0 || offset == K::kmer_len() - S::kmer_len(),
+            };
+            if is_syncmer {
+                return Some((pos, kmer));
+            }
+        }
+    }
+}
+
+/// Iterates `(position, randstrobe)` over a sequence. A randstrobe joins the
+/// canonical strobe `L` at the position with the one, among those starting
+/// `min_offset..=max_offset` bases later, whose seeded hash summed with the
+/// first's is smallest. The second strobe is also picked among those starting
+/// as far before, as the reverse strand would pick it, and the smaller of the
+/// two randstrobes kept, so a sequence and its reverse complement give the
+/// same randstrobes. Like a k-mer, each randstrobe comes from a window, here
+/// of `span` bases, so positions without `max_offset` bases of strobes on
+/// either side have none. Only the strobes of one window are held at a time.
+pub struct Randstrobes<'a, K, L> {
+    strobes: CanonicalKmers<'a, L>,
+    // the next valid strobe not in `window` yet
+    next_strobe: Option<(usize, L)>,
+    // canonical strobe and its hash by position, from `window_start` on
+    window: VecDeque<Option<(L, u64)>>,
+    window_start: usize,
+    // positions a strobe may start at
+    positions: usize,
+    min_offset: usize,
+    max_offset: usize,
+    pos: usize,
+    _mark: PhantomData<K>,
+}
+
+impl<'a, K, L> Randstrobes<'a, K, L>
+where
+    K: AbstractKMer,
+    L: AbstractKMer<Alphabet = K::Alphabet>,
+{
+    pub fn new(seq: &'a [u8], min_offset: usize, max_offset: usize) -> Self {
+        assert_eq!(
+            2 * L::kmer_len(),
+            K::kmer_len(),
+            "a randstrobe is two strobes long"
+        );
+        assert!(0 < min_offset && min_offset <= max_offset);
+        let mut strobes = CanonicalKmers::new(seq);
+        Randstrobes {
+            next_strobe: strobes.next().map(|(pos, strobe, _)| (pos, strobe)),
+            strobes,
+            window: VecDeque::with_capacity(2 * max_offset + 1),
+            window_start: 0,
+            positions: (seq.len() + 1).saturating_sub(L::kmer_len()),
+            min_offset,
+            max_offset,
+            pos: max_offset,
+            _mark: PhantomData,
+        }
+    }
+
+    // slides the window to the strobes at `pos - max_offset..=pos + max_offset`
+    fn slide(&mut self, pos: usize) {
+        while self.window_start + self.window.len() <= pos + self.max_offset {
+            let at = self.window_start + self.window.len();
+            let strobe = match self.next_strobe {
+                Some((next, strobe)) if next == at => {
+                    self.next_strobe = self.strobes.next().map(|(pos, strobe, _)| (pos, strobe));
+                    Some((strobe, hash_kmer(&strobe, STROBE_SEED)))
+                }
+                _ => None,
+            };
+            self.window.push_back(strobe);
+        }
+        while self.window_start < pos - self.max_offset {
+            self.window.pop_front();
+            self.window_start += 1;
+        }
+    }
+
+    // joins `first` with the best strobe starting in `range`, ties broken by
+    // the strobe so that the order strobes are looked at in does not matter
+    fn join(&self, (first, first_hash): (L, u64), range: Range<usize>) -> Option<K> {
+        let range = range.start - self.window_start..range.end - self.window_start;
+        let (second, _) = self
+            .window
+            .range(range)
+            .flatten()
+            .min_by_key(|&&(second, hash)| (first_hash.wrapping_add(hash), second))?;
+        let len = L::kmer_len();
+        Some((0..len).fold(K::default(), |kmer, at| {
+            kmer.with_unit(at, first.unit(at))
+                .with_unit(len + at, second.unit(at))
+        }))
+    }
+}
+
+impl<'a, K, L> Iterator for Randstrobes<'a, K, L>
+where
+    K: AbstractKMer,
+    L: AbstractKMer<Alphabet = K::Alphabet>,
+{
+    type Item = (usize, K);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos + self.max_offset < self.positions {
+            let pos = self.pos;
+            self.pos += 1;
+            self.slide(pos);
+            let first = match self.window[pos - self.window_start] {
+                Some(strobe) => strobe,
+                None => continue,
+            };
+            let forward = self.join(first, pos + self.min_offset..pos + self.max_offset + 1);
+            let reverse = self.join(first, pos - self.max_offset..pos - self.min_offset + 1);
+            if let Some(randstrobe) = forward.into_iter().chain(reverse).min() {
+                return Some((pos, randstrobe));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::KMeru128;
+    use crate::kmer::KMeru64;
+    use crate::kmer::DNA;
+    use crate::testutil::random_seq;
+    use crate::testutil::reverse_complement;
+
+    type KMer15 = KMeru64<DNA, 15>;
+    type SMer11 = KMeru64<DNA, 11>;
+    type Strobe8 = KMeru64<DNA, 8>;
+
+    fn smallest_smer(kmer: &[u8]) -> usize {
+        (0..=kmer.len() - SMer11::kmer_len())
+            .min_by_key(|&i| {
+                let smer = SMer11::from_bytes(&kmer[i..]).unwrap().get_canonical();
+                (hash_kmer(&smer, 0), smer)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    pub fn test_syncmers() {
+        let seq = random_seq(3000);
+        for &kind in &[
+            SyncmerKind::Closed,
+            SyncmerKind::Open(2),
+            SyncmerKind::Open(0),
+        ] {
+            let syncmers: Vec<_> = Syncmers::<KMer15, SMer11>::new(&seq, kind).collect();
+            let expected: Vec<_> = CanonicalKmers::<KMer15>::new(&seq)
+                .filter(|&(pos, _, _)| {
+                    let offset = smallest_smer(&seq[pos..pos + 15]);
+                    match kind {
+                        SyncmerKind::Open(t) => offset == t,
+                        SyncmerKind::Closed => offset == 0 || offset == 4,
+                    }
+                })
+                .map(|(pos, kmer, _)| (pos, kmer))
+                .collect();
+            assert_eq!(syncmers, expected);
+        }
+
+        // a closed syncmer is a syncmer on the other strand too
+        let kmer = Syncmers::<KMer15, SMer11>::new(&seq, SyncmerKind::Closed)
+            .next()
+            .unwrap()
+            .1;
+        let rc = kmer.reverse().complement().to_string();
+        let found = Syncmers::<KMer15, SMer11>::new(rc.as_bytes(), SyncmerKind::Closed).next();
+        assert_eq!(found, Some((0, kmer)));
+    }
+
+    #[test]
+    pub fn test_randstrobes() {
+        let seq = random_seq(1000);
+        let (min_offset, max_offset) = (8, 20);
+        let strobes: Vec<_> =
+            Randstrobes::<KMeru64<DNA, 16>, Strobe8>::new(&seq, min_offset, max_offset).collect();
+
+        let strobe_at = |pos: usize| {
+            Strobe8::from_bytes(seq.get(pos..)?)
+                .ok()
+                .map(|strobe| strobe.get_canonical())
+        };
+        let join = |first: Strobe8, seconds: Vec<usize>| {
+            let second = seconds
+                .into_iter()
+                .filter_map(strobe_at)
+                .min_by_key(|strobe| {
+                    let key = hash_kmer(&first, 0).wrapping_add(hash_kmer(strobe, 0));
+                    (key, *strobe)
+                })?;
+            let randstrobe = format!("{}{}", first, second);
+            Some(randstrobe.parse::<KMeru64<DNA, 16>>().unwrap())
+        };
+        // only positions with a whole window of strobes around them
+        let mut expected = vec![];
+        for pos in max_offset..seq.len() + 1 - max_offset - Strobe8::kmer_len() {
+            let first = match strobe_at(pos) {
+                Some(strobe) => strobe,
+                None => continue,
+            };
+            let after = join(first, (pos + min_offset..=pos + max_offset).collect());
+            let before = join(first, (pos - max_offset..=pos - min_offset).collect());
+            if let Some(randstrobe) = after.into_iter().chain(before).min() {
+                expected.push((pos, randstrobe));
+            }
+        }
+        assert_eq!(strobes, expected);
+
+        // the reverse complement gives the same randstrobes
+        let rc = reverse_complement(&seq);
+        let mut reverse: Vec<_> =
+            Randstrobes::<KMeru64<DNA, 16>, Strobe8>::new(&rc, min_offset, max_offset)
+                .map(|(pos, kmer)| (seq.len() - Strobe8::kmer_len() - pos, kmer))
+                .collect();
+        reverse.sort_by_key(|&(pos, _)| pos);
+        assert_eq!(reverse, strobes);
+
+        // strobes join the same in a wider k-mer type
+        let wide: Vec<_> =
+            Randstrobes::<KMeru128<DNA, 16>, Strobe8>::new(&seq, min_offset, max_offset)
+                .map(|(pos, kmer)| (pos, kmer.to_string()))
+                .collect();
+        let narrow: Vec<_> = strobes
+            .iter()
+            .map(|(pos, kmer)| (*pos, kmer.to_string()))
+            .collect();
+        assert_eq!(wide, narrow);
+    }
+}
@@ -0,0 +1,277 @@
+use std::marker::PhantomData;
+
+use crate::kmer::AbstractKMer;
+use crate::kmer::Alphabet;
+use crate::kmer::Strand;
+
+/// Care (`1`) and don't-care (`0`) positions of a spaced seed such as
+/// `1101101`. The spaced k-mer of a window is its care bases, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpacedSeed {
+    span: usize,
+    // bit span-1-i set when position i is a care position
+    care: u128,
+    // (first position, length) of each run of care positions
+    blocks: Vec<(usize, usize)>,
+}
+
+impl SpacedSeed {
+    /// Longest span, so the windows of 2-bit alphabets fit in a `u128`.
+    pub const MAX_SPAN: usize = 64;
+
+    /// Parses a pattern of `0` and `1` that starts and ends with `1`.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let bytes = pattern.as_bytes();
+        if bytes.len() > Self::MAX_SPAN
+            || bytes.first() != Some(&b'1')
+            || bytes.last() != Some(&b'1')
+            || bytes.iter().any(|&b| b != b'0' && b != b'1')
+        {
+            return None;
+        }
+        let mut care = 0u128;
+        let mut blocks: Vec<(usize, usize)> = vec![];
+        for (i, &b) in bytes.iter().enumerate() {
+            care = care << 1 | (b == b'1') as u128;
+            if b == b'0' {
+                continue;
+            }
+            match blocks.last_mut() {
+                Some((start, len)) if *start + *len == i => *len += 1,
+                _ => blocks.push((i, 1)),
+            }
+        }
+        Some(SpacedSeed {
+            span: bytes.len(),
+            care,
+            blocks,
+        })
+    }
+
+    /// Bases a window covers.
+    pub fn span(&self) -> usize {
+        self.span
+    }
+
+    /// Care positions, the length of the spaced k-mers.
+    pub fn weight(&self) -> usize {
+        self.care.count_ones() as usize
+    }
+
+    // care positions of the seed laid on the reverse complement window
+    fn reversed_care(&self) -> u128 {
+        self.care.reverse_bits() >> (128 - self.span)
+    }
+
+    // gathers the care units of a window of units right-aligned in `window`
+    fn extract(&self, window: u128, unit_len: usize) -> u128 {
+        let mut units = 0u128;
+        for &(start, len) in self.blocks.iter() {
+            let bits = unit_len * len;
+            let block = window >> (unit_len * (self.span - start - len));
+            units =
+                units.checked_shl(bits as u32).unwrap_or(0) | block & (u128::MAX >> (128 - bits));
+        }
+        units
+    }
+}
+
+/// Iterates `(position, canonical spaced k-mer, strand)` over the windows of
+/// a sequence, where `K` is as long as the seed's weight. The forward and
+/// reverse complement windows are kept rolling and the care units gathered
+/// in one step per run of care positions. The canonical form is the smaller
+/// of the seed applied to the forward window and to its reverse complement,
+/// so a sequence and its reverse complement give the same spaced k-mers.
+/// Bases the alphabet rejects only drop the windows that have them at a care
+/// position on either strand.
+pub struct SpacedKmers<'a, K> {
+    seq: &'a [u8],
+    seed: &'a SpacedSeed,
+    // care positions of either strand, in window order
+    care: u128,
+    pos: usize,
+    forward: u128,
+    reverse: u128,
+    invalid: u128, // bit set for each rejected base in the window
+    _mark: PhantomData<K>,
+}
+
+impl<'a, K> SpacedKmers<'a, K>
+where
+    K: AbstractKMer,
+{
+    pub fn new(seq: &'a [u8], seed: &'a SpacedSeed) -> Self {
+        assert_eq!(
+            K::kmer_len(),
+            seed.weight(),
+            "k-mer length is not the seed weight"
+        );
+        assert!(
+            seed.span * K::Alphabet::UNIT_LEN <= 128,
+            "seed span does not fit the window"
+        );
+        SpacedKmers {
+            seq,
+            seed,
+            care: if K::has_complement() {
+                seed.care | seed.reversed_care()
+            } else {
+                seed.care
+            },
+            pos: 0,
+            forward: 0,
+            reverse: 0,
+            invalid: 0,
+            _mark: PhantomData,
+        }
+    }
+}
+
+impl<'a, K> Iterator for SpacedKmers<'a, K>
+where
+    K: AbstractKMer,
+{
+    type Item = (usize, K, Strand);
+    fn next(&mut self) -> Option<Self::Item> {
+        let unit_len = K::Alphabet::UNIT_LEN;
+        let span = self.seed.span;
+        let window_mask = u128::MAX >> (128 - unit_len * span);
+        while self.pos < self.seq.len() {
+            let base = self.seq[self.pos];
+            self.pos += 1;
+            let unit = K::Alphabet::to_unit(base);
+            // rejected bases take unit 0, they are never gathered
+            let fwd_unit = unit.unwrap_or(0) as u128;
+            self.forward = (self.forward << unit_len | fwd_unit) & window_mask;
+            self.invalid =
+                (self.invalid << 1 | unit.is_none() as u128) & (u128::MAX >> (128 - span));
+            if K::has_complement() {
+                let rc_unit = unit.map_or(0, K::Alphabet::complement) as u128;
+                self.reverse = self.reverse >> unit_len | rc_unit << (unit_len * (span - 1));
+            }
+            if self.pos < span || self.invalid & self.care != 0 {
+                continue;
+            }
+            let start = self.pos - span;
+            let forward = self.seed.extract(self.forward, unit_len);
+            if !K::has_complement() {
+                return Some((start, K::from_u128(forward), Strand::Forward));
+            }
+            let reverse = self.seed.extract(self.reverse, unit_len);
+            return Some(if forward <= reverse {
+                (start, K::from_u128(forward), Strand::Forward)
+            } else {
+                (start, K::from_u128(reverse), Strand::Reverse)
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::CanonicalKmers;
+    use crate::kmer::KMeru128;
+    use crate::kmer::KMeru64;
+    use crate::kmer::Protein;
+    use crate::kmer::DNA;
+    use crate::testutil::random_seq;
+    use crate::testutil::reverse_complement;
+
+    // the smaller of the seed applied to each window and to its reverse
+    // complement, if both hold only valid bases
+    fn brute_force<K: AbstractKMer>(seq: &[u8], pattern: &str) -> Vec<(usize, K)> {
+        let care = |window: &[u8]| -> Vec<u8> {
+            pattern
+                .bytes()
+                .zip(window)
+                .filter(|(p, _)| *p == b'1')
+                .map(|(_, &b)| b)
+                .collect()
+        };
+        let mut ret = vec![];
+        for start in 0..(seq.len() + 1).saturating_sub(pattern.len()) {
+            let window = &seq[start..start + pattern.len()];
+            let forward = K::from_bytes(&care(window));
+            if !K::has_complement() {
+                ret.extend(forward.ok().map(|kmer| (start, kmer)));
+                continue;
+            }
+            let reverse = K::from_bytes(&care(&reverse_complement(window)));
+            if let (Ok(forward), Ok(reverse)) = (forward, reverse) {
+                ret.push((start, forward.min(reverse)));
+            }
+        }
+        ret
+    }
+
+    fn spaced<K: AbstractKMer>(seq: &[u8], pattern: &str) -> Vec<(usize, K)> {
+        let seed = SpacedSeed::parse(pattern).unwrap();
+        SpacedKmers::<K>::new(seq, &seed)
+            .map(|(pos, kmer, _)| (pos, kmer))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_seed_parse() {
+        let seed = SpacedSeed::parse("1101101").unwrap();
+        assert_eq!(seed.span(), 7);
+        assert_eq!(seed.weight(), 5);
+        assert!(SpacedSeed::parse("0110").is_none());
+        assert!(SpacedSeed::parse("1x1").is_none());
+        assert!(SpacedSeed::parse("").is_none());
+        assert!(SpacedSeed::parse(&"1".repeat(65)).is_none());
+    }
+
+    #[test]
+    pub fn test_spaced_kmers() {
+        let seq = random_seq(3000);
+        let pattern = "1101101";
+        assert_eq!(
+            spaced::<KMeru64<DNA, 5>>(&seq, pattern),
+            brute_force::<KMeru64<DNA, 5>>(&seq, pattern)
+        );
+        let pattern = "111010010100110111";
+        assert_eq!(
+            spaced::<KMeru64<DNA, 11>>(&seq, pattern),
+            brute_force::<KMeru64<DNA, 11>>(&seq, pattern)
+        );
+        let pattern = format!("{}0{}", "1".repeat(31), "1".repeat(32));
+        assert_eq!(
+            spaced::<KMeru128<DNA, 63>>(&seq, &pattern),
+            brute_force::<KMeru128<DNA, 63>>(&seq, &pattern)
+        );
+
+        let contiguous: Vec<_> = CanonicalKmers::<KMeru64<DNA, 9>>::new(&seq)
+            .map(|(pos, kmer, _)| (pos, kmer))
+            .collect();
+        assert_eq!(spaced::<KMeru64<DNA, 9>>(&seq, "111111111"), contiguous);
+
+        let peptide = b"MKTAYIAKQRQISFVKSHFSRQXLEERLGLIEVQ";
+        assert_eq!(
+            spaced::<KMeru64<Protein, 4>>(peptide, "110011"),
+            brute_force::<KMeru64<Protein, 4>>(peptide, "110011")
+        );
+    }
+
+    #[test]
+    pub fn test_spaced_kmers_reverse_complement() {
+        let seq = random_seq(3000);
+        let rc = reverse_complement(&seq);
+        for pattern in ["111010010100110111", "1111110000011111", "11111111111"] {
+            let mut forward: Vec<_> = spaced::<KMeru64<DNA, 11>>(&seq, pattern)
+                .into_iter()
+                .map(|(_, kmer)| kmer)
+                .collect();
+            let mut reverse: Vec<_> = spaced::<KMeru64<DNA, 11>>(&rc, pattern)
+                .into_iter()
+                .map(|(_, kmer)| kmer)
+                .collect();
+            forward.sort();
+            reverse.sort();
+            assert!(!forward.is_empty());
+            assert_eq!(forward, reverse, "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::borrow::Cow;
+
+use crate::kmer::AbstractKMer;
+use crate::kmer::Alphabet;
+use crate::kmer::CanonicalKmers;
+
+/// How lowercase (soft-masked) bases are treated when splitting reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaskPolicy {
+    /// Masked bases break k-mers like any invalid base.
+    SkipMasked,
+    /// Masked bases count as their uppercase base.
+    IgnoreCase,
+}
+
+/// How IUPAC ambiguity codes (`N`, `R`, `Y`, ...) are treated when splitting reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmbiguityPolicy {
+    /// Drop every k-mer covering an ambiguous base.
+    Split,
+    /// Emit every concrete variant of a k-mer, dropping k-mers with more
+    /// than the given number of variants.
+    Expand(usize),
+    /// Read every ambiguous base as the given base, an uppercase base of the
+    /// alphabet split by, see `check`.
+    Substitute(u8),
+}
+
+impl AmbiguityPolicy {
+    /// Whether the policy applies to k-mers over the alphabet `A`, and why not.
+    pub fn check<A: Alphabet>(&self) -> Result<(), String> {
+        match *self {
+            AmbiguityPolicy::Substitute(sub)
+                if sub.is_ascii_lowercase() || A::to_unit(sub).is_none() =>
+            {
+                Err(format!(
+                    "cannot substitute '{}' for ambiguous bases",
+                    (sub as char).escape_default()
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Bases that did not make it into any k-mer, by the rule that dropped them,
+/// and k-mers lost to ambiguity codes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SplitStats {
+    pub masked_bases: usize,
+    pub invalid_bases: usize,
+    pub ambiguous_bases: usize,
+    pub ambiguous_kmers: usize,
+}
+
+/// Concrete bases of an IUPAC nucleotide ambiguity code.
+pub fn iupac_bases(code: u8) -> Option<&'static [u8]> {
+    Some(match code.to_ascii_uppercase() {
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => return None,
+    })
+}
+
+pub struct KMerSplitter {
+    mask: MaskPolicy,
+    ambiguity: AmbiguityPolicy,
+    stats: SplitStats,
+}
+
+impl KMerSplitter {
+    pub fn new(mask: MaskPolicy, ambiguity: AmbiguityPolicy) -> Self {
+        KMerSplitter {
+            mask,
+            ambiguity,
+            stats: SplitStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> SplitStats {
+        self.stats
+    }
+
+    fn is_masked(&self, base: u8) -> bool {
+        self.mask == MaskPolicy::SkipMasked && base.is_ascii_lowercase()
+    }
+
+    fn is_ambiguous<K: AbstractKMer>(base: u8) -> bool {
+        K::Alphabet::to_unit(base).is_none() && iupac_bases(base).is_some()
+    }
+
+    /// Calls `emit` with the canonical form of every k-mer in `read`. A k-mer
+    /// restarts after each base dropped by the policy.
+    pub fn split<K, F>(&mut self, read: &[u8], mut emit: F)
+    where
+        K: AbstractKMer,
+        F: FnMut(K),
+    {
+        self.split_runs::<K, _>(read, |run| {
+            for (_, kmer, _) in CanonicalKmers::<K>::new(run) {
+                emit(kmer);
+            }
+        });
+    }
+
+    /// Like `split`, but calls `emit` with the runs of bases the k-mers come
+    /// from instead. Runs may hold ambiguity codes, which `CanonicalKmers`
+    /// restarts at, so the runs yield exactly the k-mers `split` emits.
+    /// Panics if the ambiguity policy fails `AmbiguityPolicy::check` for
+    /// `K`'s alphabet.
+    pub fn split_runs<K, F>(&mut self, read: &[u8], mut emit: F)
+    where
+        K: AbstractKMer,
+        F: FnMut(&[u8]),
+    {
+        let read = match self.ambiguity {
+            AmbiguityPolicy::Substitute(sub) => {
+                if let Err(e) = self.ambiguity.check::<K::Alphabet>() {
+                    panic!("{}", e);
+                }
+                let mut substituted = Cow::Borrowed(read);
+                for (i, &base) in read.iter().enumerate() {
+                    if !self.is_masked(base) && Self::is_ambiguous::<K>(base) {
+                        self.stats.ambiguous_bases += 1;
+                        substituted.to_mut()[i] = sub;
+                    }
+                }
+                substituted
+            }
+            _ => Cow::Borrowed(read),
+        };
+
+        // cut the read at dropped bases, ambiguous ones stay in the segments
+        let mut segment_start = 0;
+        let mut ambiguous = 0;
+        for (i, &base) in read.iter().enumerate() {
+            if self.is_masked(base) {
+                self.stats.masked_bases += 1;
+            } else if K::Alphabet::to_unit(base).is_some() {
+                continue;
+            } else if iupac_bases(base).is_some() {
+                ambiguous += 1;
+                continue;
+            } else {
+                self.stats.invalid_bases += 1;
+            }
+            self.split_segment::<K, _>(&read[segment_start..i], ambiguous, &mut emit);
+            segment_start = i + 1;
+            ambiguous = 0;
+        }
+        self.split_segment::<K, _>(&read[segment_start..], ambiguous, &mut emit);
+    }
+
+    fn split_segment<K, F>(&mut self, segment: &[u8], ambiguous: usize, emit: &mut F)
+    where
+        K: AbstractKMer,
+        F: FnMut(&[u8]),
+    {
+        // ambiguous bases restart the rolling k-mer, so this only yields
+        // the k-mers without them
+        emit(segment);
+        if ambiguous == 0 {
+            return;
+        }
+
+        self.stats.ambiguous_bases += ambiguous;
+        let k = K::kmer_len();
+        let mut last_ambiguous = None;
+        for (i, &base) in segment.iter().enumerate() {
+            if Self::is_ambiguous::<K>(base) {
+                last_ambiguous = Some(i);
+            }
+            if i + 1 < k || !matches!(last_ambiguous, Some(a) if a + k > i) {
+                continue;
+            }
+            match self.ambiguity {
+                AmbiguityPolicy::Expand(max_variants) => {
+                    self.expand::<K, _>(&segment[i + 1 - k..=i], max_variants, emit)
+                }
+                _ => self.stats.ambiguous_kmers += 1,
+            }
+        }
+    }
+
+    fn expand<K, F>(&mut self, window: &[u8], max_variants: usize, emit: &mut F)
+    where
+        K: AbstractKMer,
+        F: FnMut(&[u8]),
+    {
+        let ambiguous: Vec<(usize, &[u8])> = window
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| Self::is_ambiguous::<K>(b))
+            .map(|(i, &b)| (i, iupac_bases(b).unwrap()))
+            .collect();
+        let variants = ambiguous
+            .iter()
+            .fold(1usize, |acc, (_, bases)| acc.saturating_mul(bases.len()));
+        if variants > max_variants {
+            self.stats.ambiguous_kmers += 1;
+            return;
+        }
+
+        // count through the variants in mixed radix
+        let mut buf = window.to_vec();
+        let mut digits = vec![0; ambiguous.len()];
+        for _ in 0..variants {
+            for (&(pos, bases), &d) in ambiguous.iter().zip(digits.iter()) {
+                buf[pos] = bases[d];
+            }
+            emit(&buf);
+            for (d, (_, bases)) in digits.iter_mut().zip(ambiguous.iter()) {
+                *d += 1;
+                if *d < bases.len() {
+                    break;
+                }
+                *d = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::KMeru64;
+    use crate::kmer::DNA;
+    use crate::kmer::RNA;
+
+    type KMer5 = KMeru64<DNA, 5>;
+    type KMer3 = KMeru64<DNA, 3>;
+
+    fn split<K: AbstractKMer + ToString>(
+        mask: MaskPolicy,
+        ambiguity: AmbiguityPolicy,
+        read: &str,
+    ) -> (Vec<String>, SplitStats) {
+        let mut splitter = KMerSplitter::new(mask, ambiguity);
+        let mut kmers = vec![];
+        splitter.split(read.as_bytes(), |k: K| kmers.push(k.to_string()));
+        (kmers, splitter.stats())
+    }
+
+    #[test]
+    pub fn test_split_canonical() {
+        let (kmers, stats) =
+            split::<KMer5>(MaskPolicy::SkipMasked, AmbiguityPolicy::Split, "TTTTTGA");
+        assert_eq!(kmers, vec!["AAAAA", "CAAAA", "TCAAA"]);
+        assert_eq!(stats.invalid_bases, 0);
+    }
+
+    #[test]
+    pub fn test_split_soft_masked() {
+        let read = "ACGTAacgtACGTA-ACGTA";
+        let (kmers, stats) = split::<KMer5>(MaskPolicy::SkipMasked, AmbiguityPolicy::Split, read);
+        assert_eq!(kmers, vec!["ACGTA", "ACGTA", "ACGTA"]);
+        assert_eq!(stats.masked_bases, 4);
+        assert_eq!(stats.invalid_bases, 1);
+
+        let (kmers, stats) = split::<KMer5>(MaskPolicy::IgnoreCase, AmbiguityPolicy::Split, read);
+        assert_eq!(kmers.len(), 10 + 1);
+        assert_eq!(stats.masked_bases, 0);
+        assert_eq!(stats.invalid_bases, 1);
+    }
+
+    #[test]
+    pub fn test_split_ambiguous() {
+        let read = "ACGRTAC";
+        let (kmers, stats) = split::<KMer3>(MaskPolicy::SkipMasked, AmbiguityPolicy::Split, read);
+        assert_eq!(kmers, vec!["ACG", "GTA"]);
+        assert_eq!(stats.ambiguous_bases, 1);
+        assert_eq!(stats.ambiguous_kmers, 3);
+        assert_eq!(stats.invalid_bases, 0);
+
+        let (kmers, stats) =
+            split::<KMer3>(MaskPolicy::SkipMasked, AmbiguityPolicy::Expand(2), read);
+        assert_eq!(
+            kmers,
+            vec!["ACG", "GTA", "CGA", "CCG", "ATC", "ACC", "ATA", "GTA"]
+        );
+        assert_eq!(stats.ambiguous_kmers, 0);
+
+        let (kmers, stats) =
+            split::<KMer3>(MaskPolicy::SkipMasked, AmbiguityPolicy::Expand(1), read);
+        assert_eq!(kmers.len(), 2);
+        assert_eq!(stats.ambiguous_kmers, 3);
+
+        let (kmers, stats) = split::<KMer3>(
+            MaskPolicy::SkipMasked,
+            AmbiguityPolicy::Substitute(b'A'),
+            read,
+        );
+        assert_eq!(kmers, vec!["ACG", "CGA", "ATC", "ATA", "GTA"]);
+        assert_eq!(stats.ambiguous_bases, 1);
+        assert_eq!(stats.ambiguous_kmers, 0);
+        assert!(AmbiguityPolicy::Substitute(b'U').check::<DNA>().is_err());
+        assert!(AmbiguityPolicy::Substitute(b'U').check::<RNA>().is_ok());
+        assert!(AmbiguityPolicy::Substitute(b'a').check::<DNA>().is_err());
+        assert!(AmbiguityPolicy::Substitute(b'N').check::<DNA>().is_err());
+
+        // soft-masked ambiguity codes stay masked
+        let (kmers, stats) = split::<KMer3>(
+            MaskPolicy::SkipMasked,
+            AmbiguityPolicy::Expand(4),
+            "ACGnTAC",
+        );
+        assert_eq!(kmers, vec!["ACG", "GTA"]);
+        assert_eq!(stats.masked_bases, 1);
+        assert_eq!(stats.ambiguous_bases, 0);
+    }
+}
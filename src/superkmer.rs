@@ -0,0 +1,166 @@
+use crate::kmer::AbstractKMer;
+use crate::kmer::Alphabet;
+use crate::minimizer::MinimizerOrder;
+use crate::minimizer::Minimizers;
+
+/// Minimizer length super-k-mers are routed by, k must be at least this long.
+pub const MINIMIZER_LEN: usize = 9;
+// must stay strand independent, or the two strands of a k-mer part ways
+pub const MINIMIZER_ORDER: MinimizerOrder = MinimizerOrder::Hash(0);
+
+/// Splits a sequence into super-k-mers, maximal runs of consecutive k-mers
+/// sharing a minimizer occurrence, and yields `(minimizer, super-k-mer bases)`.
+/// With a strand independent order every occurrence of a canonical k-mer gets
+/// the same minimizer, so routing by it keeps the counts together.
+pub struct SuperKmers<'a, M> {
+    seq: &'a [u8],
+    k: usize,
+    minimizers: Minimizers<'a, M>,
+    // first window, minimizer position and minimizer of the open super-k-mer
+    pending: Option<(usize, usize, M)>,
+    last_window: usize,
+}
+
+impl<'a, M> SuperKmers<'a, M>
+where
+    M: AbstractKMer,
+{
+    pub fn new(seq: &'a [u8], k: usize, order: MinimizerOrder) -> Self {
+        assert!(k >= M::kmer_len(), "minimizer is longer than the k-mers");
+        SuperKmers {
+            seq,
+            k,
+            minimizers: Minimizers::new(seq, k - M::kmer_len() + 1, order),
+            pending: None,
+            last_window: 0,
+        }
+    }
+
+    fn close(&self, first_window: usize, minimizer: M) -> (M, &'a [u8]) {
+        (
+            minimizer,
+            &self.seq[first_window..self.last_window + self.k],
+        )
+    }
+}
+
+impl<'a, M> Iterator for SuperKmers<'a, M>
+where
+    M: AbstractKMer,
+{
+    type Item = (M, &'a [u8]);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (window, pos, minimizer) = match self.minimizers.next() {
+                Some(next) => next,
+                None => {
+                    let (first, _, minimizer) = self.pending.take()?;
+                    return Some(self.close(first, minimizer));
+                }
+            };
+            // windows sharing a minimizer position are always consecutive
+            let closed = match self.pending {
+                Some((_, pending_pos, _)) if pending_pos == pos => None,
+                _ => self.pending.replace((window, pos, minimizer)),
+            };
+            let ret = closed.map(|(first, _, minimizer)| self.close(first, minimizer));
+            self.last_window = window;
+            if ret.is_some() {
+                return ret;
+            }
+        }
+    }
+}
+
+/// Appends `bases` to `packed` as its length followed by its units, most
+/// significant first. Units never straddle words.
+pub fn pack<A: Alphabet>(packed: &mut Vec<u64>, bases: &[u8]) {
+    packed.push(bases.len() as u64);
+    let mut word = 0u64;
+    let mut bits = 0;
+    for &base in bases {
+        if bits + A::UNIT_LEN > 64 {
+            packed.push(word << (64 - bits));
+            word = 0;
+            bits = 0;
+        }
+        let unit = A::to_unit(base).expect("super-k-mers only hold valid bases");
+        word = word << A::UNIT_LEN | unit as u64;
+        bits += A::UNIT_LEN;
+    }
+    if bits > 0 {
+        packed.push(word << (64 - bits));
+    }
+}
+
+/// Calls `emit` with the bases of every sequence `pack` appended to `packed`.
+pub fn unpack<A: Alphabet, F: FnMut(&[u8])>(packed: &[u64], mut emit: F) {
+    let units_per_word = 64 / A::UNIT_LEN;
+    let mask = (1u64 << A::UNIT_LEN) - 1;
+    let mut bases = vec![];
+    let mut i = 0;
+    while i < packed.len() {
+        let len = packed[i] as usize;
+        i += 1;
+        bases.clear();
+        for j in 0..len {
+            let word = packed[i + j / units_per_word];
+            let shift = 64 - A::UNIT_LEN * (j % units_per_word + 1);
+            bases.push(A::to_u8((word >> shift & mask) as u8).unwrap());
+        }
+        i += len.div_ceil(units_per_word);
+        emit(&bases);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kmer::CanonicalKmers;
+    use crate::kmer::KMeru64;
+    use crate::kmer::Protein;
+    use crate::kmer::DNA;
+
+    type KMer11 = KMeru64<DNA, 11>;
+    type MMer4 = KMeru64<DNA, 4>;
+
+    #[test]
+    pub fn test_super_kmers() {
+        let read = b"GATTACAGATTACAGGCTTCAGCTACGATCNAGCATTAGCTTTTTTTTTTTTTTTACGGACTGA";
+        let mut kmers = vec![];
+        let mut lengths = vec![];
+        for (minimizer, superkmer) in SuperKmers::<MMer4>::new(read, 11, MINIMIZER_ORDER) {
+            lengths.push(superkmer.len());
+            // every window inside the super-k-mer agrees on the minimizer
+            for (_, _, m) in Minimizers::<MMer4>::new(superkmer, 8, MINIMIZER_ORDER) {
+                assert_eq!(m, minimizer);
+            }
+            kmers.extend(CanonicalKmers::<KMer11>::new(superkmer).map(|(_, k, _)| k));
+        }
+        let expected: Vec<_> = CanonicalKmers::<KMer11>::new(read)
+            .map(|(_, k, _)| k)
+            .collect();
+        assert_eq!(kmers, expected);
+        assert!(lengths.len() < expected.len());
+        assert!(lengths.iter().all(|&l| (11..=11 + 7).contains(&l)));
+    }
+
+    #[test]
+    pub fn test_pack() {
+        let seqs: Vec<&[u8]> = vec![b"ACGTACGTACGTACGTACGTACGTACGTACGTA", b"", b"T", b"GGC"];
+        let mut packed = vec![];
+        for seq in &seqs {
+            pack::<DNA>(&mut packed, seq);
+        }
+        assert_eq!(packed.len(), 4 + 2 + 1 + 1);
+        let mut unpacked = vec![];
+        unpack::<DNA, _>(&packed, |bases| unpacked.push(bases.to_vec()));
+        assert_eq!(unpacked, seqs);
+
+        let peptide = b"MKVLAAGIVGLLLAQWERTY";
+        let mut packed = vec![];
+        pack::<Protein>(&mut packed, peptide);
+        assert_eq!(packed.len(), 1 + 2);
+        unpack::<Protein, _>(&packed, |bases| assert_eq!(bases, peptide));
+    }
+}
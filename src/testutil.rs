@@ -0,0 +1,34 @@
+//! Helpers shared by the unit tests.
+
+/// Xorshift, enough to make test data.
+pub fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// `len` random bases, the same on every call, with an `N` about every 61.
+pub fn random_seq(len: usize) -> Vec<u8> {
+    let mut state = 0x0123456789ABCDEF;
+    (0..len)
+        .map(|_| match next_random(&mut state) % 61 {
+            0 => b'N',
+            r => b"ACGT"[r as usize % 4],
+        })
+        .collect()
+}
+
+/// Reverse complement of DNA, bytes other than `ACGT` kept as they are.
+pub fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&b| match b {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            b => b,
+        })
+        .collect()
+}